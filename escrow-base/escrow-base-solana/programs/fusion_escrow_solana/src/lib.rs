@@ -1,677 +1,4864 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer as SplTransfer};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    self, spl_token_2022, CloseAccount, HarvestWithheldTokensToMint, Mint, TokenAccount, TokenInterface,
+};
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
 use anchor_lang::solana_program::system_instruction;
+use anchor_lang::solana_program::{ed25519_program, secp256k1_program, sysvar::instructions as instructions_sysvar};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"); // Placeholder, will be updated after first build
 
 const ORDER_PDA_SEED_PREFIX_SOURCE: &[u8] = b"order_source";
 const ORDER_PDA_SEED_PREFIX_DESTINATION: &[u8] = b"order_destination";
 const ESCROW_AUTHORITY_SEED_PREFIX: &[u8] = b"escrow_authority";
+const INTENT_NONCE_SEED_PREFIX: &[u8] = b"intent_nonce";
+const CONFIG_SEED: &[u8] = b"config";
+const RESOLVER_SEED_PREFIX: &[u8] = b"resolver";
+const STAKE_SEED_PREFIX: &[u8] = b"stake";
+const NULLIFIER_SEED_PREFIX: &[u8] = b"nullifier";
+const SECRET_SEED_PREFIX: &[u8] = b"secret";
+
+const ORDER_INTENT_DOMAIN: &[u8] = b"fusion_escrow_solana:order_intent";
+const EVM_WITHDRAWAL_DOMAIN: &[u8] = b"fusion_escrow_solana:evm_withdrawal";
+const ORDER_ID_DOMAIN: &[u8] = b"fusion_escrow_solana:order_id";
+
+const BPS_DENOMINATOR: u16 = 10_000;
+const MAX_AUCTION_POINTS: usize = 8;
+const MAX_FOREIGN_ADDRESS_LEN: usize = 32; // Fits EVM (20 bytes) and Solana-style (32 bytes) addresses
+const SOLANA_CHAIN_ID: u64 = 501; // Id cross-chain aggregators use for Solana mainnet
+const NATIVE_SOL_DECIMALS: u8 = 9;
+const DONATION_METADATA_VERSION: u8 = 1;
+const MAX_LINK_ID_LEN: usize = 32;
+const MAX_DONATION_MESSAGE_LEN: usize = 280; // Bytes of UTF-8, not characters
+const MAX_DONOR_NAME_LEN: usize = 32;
 
 #[program]
 pub mod fusion_escrow_solana {
     use super::*;
 
+    // --- Protocol Admin ---
+
+    /// Creates the singleton protocol config. Only the program's upgrade authority may call this, once.
+    pub fn initialize_config(
+        ctx: Context<InitializeConfigAccounts>,
+        admin: Pubkey,
+        treasury: Pubkey,
+        protocol_fee_bps: u16,
+        relayer_reward_bps: u16,
+        limits: ProtocolLimits,
+        staking: StakingParams,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = admin;
+        config.pending_admin = None;
+        config.treasury = treasury;
+        config.protocol_fee_bps = protocol_fee_bps;
+        config.relayer_reward_bps = relayer_reward_bps;
+        config.paused = false;
+        config.limits = limits;
+        config.staking = staking;
+        config.bump = ctx.bumps.config;
+        config.validate()?;
+
+        emit!(config.updated_event());
+        Ok(())
+    }
+
+    /// Stops (or resumes) new orders. Withdrawals and cancellations of existing orders are never paused.
+    pub fn set_paused(ctx: Context<UpdateConfigAccounts>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.paused = paused;
+
+        emit!(config.updated_event());
+        Ok(())
+    }
+
+    pub fn update_limits(ctx: Context<UpdateConfigAccounts>, limits: ProtocolLimits) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.limits = limits;
+        config.validate()?;
+
+        emit!(config.updated_event());
+        Ok(())
+    }
+
+    pub fn update_staking_params(ctx: Context<UpdateConfigAccounts>, staking: StakingParams) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.staking = staking;
+        config.validate()?;
+
+        emit!(config.updated_event());
+        Ok(())
+    }
+
+    pub fn set_treasury(ctx: Context<UpdateConfigAccounts>, treasury: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.treasury = treasury;
+
+        emit!(config.updated_event());
+        Ok(())
+    }
+
+    /// Sets the fee taken on every settlement of orders created from now on; existing orders keep their rate.
+    pub fn set_protocol_fee(ctx: Context<UpdateConfigAccounts>, protocol_fee_bps: u16) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.protocol_fee_bps = protocol_fee_bps;
+        config.validate()?;
+
+        emit!(config.updated_event());
+        Ok(())
+    }
+
+    /// Sets the share of the safety deposit paid to whoever relays a destination withdrawal for the maker.
+    pub fn set_relayer_reward(ctx: Context<UpdateConfigAccounts>, relayer_reward_bps: u16) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.relayer_reward_bps = relayer_reward_bps;
+        config.validate()?;
+
+        emit!(config.updated_event());
+        Ok(())
+    }
+
+    /// First step of an admin handover; takes effect once `new_admin` calls `accept_admin`.
+    pub fn transfer_admin(ctx: Context<UpdateConfigAccounts>, new_admin: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.pending_admin = Some(new_admin);
+
+        emit!(AdminTransferProposed {
+            admin: config.admin,
+            pending_admin: new_admin,
+        });
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdminAccounts>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let previous_admin = config.admin;
+        config.admin = ctx.accounts.new_admin.key();
+        config.pending_admin = None;
+
+        emit!(AdminTransferred {
+            previous_admin,
+            new_admin: config.admin,
+        });
+        Ok(())
+    }
+
+    /// Adds a vetted resolver to the registry as active.
+    pub fn register_resolver(ctx: Context<RegisterResolverAccounts>, resolver: Pubkey) -> Result<()> {
+        let record = &mut ctx.accounts.resolver_record;
+        record.resolver = resolver;
+        record.status = ResolverStatus::Active;
+        record.registered_at = Clock::get()?.unix_timestamp;
+        record.bump = ctx.bumps.resolver_record;
+
+        emit!(ResolverStatusChanged {
+            resolver,
+            status: record.status,
+        });
+        Ok(())
+    }
+
+    /// Suspending a resolver stops new orders naming it and new destination fills from it;
+    /// orders it already holds can still be withdrawn or cancelled.
+    pub fn set_resolver_status(ctx: Context<SetResolverStatusAccounts>, _resolver: Pubkey, status: ResolverStatus) -> Result<()> {
+        let record = &mut ctx.accounts.resolver_record;
+        record.status = status;
+
+        emit!(ResolverStatusChanged {
+            resolver: record.resolver,
+            status,
+        });
+        Ok(())
+    }
+
+    // --- Resolver Staking ---
+
+    pub fn deposit_stake(ctx: Context<DepositStakeAccounts>, amount: u64) -> Result<()> {
+        require_gt!(amount, 0, EscrowError::ZeroAmount);
+
+        let stake = &mut ctx.accounts.resolver_stake;
+        stake.resolver = ctx.accounts.resolver.key();
+        stake.bump = ctx.bumps.resolver_stake;
+        stake.staked_amount = stake.staked_amount.checked_add(amount).ok_or(EscrowError::Overflow)?;
+
+        // Stake is held as lamports in the resolver_stake PDA, on top of its rent-exempt reserve
+        let ix = system_instruction::transfer(&ctx.accounts.resolver.key(), &stake.key(), amount);
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.resolver.to_account_info(),
+                stake.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        emit!(StakeDeposited {
+            resolver: stake.resolver,
+            amount,
+            staked_amount: stake.staked_amount,
+        });
+        Ok(())
+    }
+
+    /// Moves stake into the unstaking queue. It stays slashable until `withdraw_stake` after the cooldown.
+    pub fn request_unstake(ctx: Context<RequestUnstakeAccounts>, amount: u64) -> Result<()> {
+        require_gt!(amount, 0, EscrowError::ZeroAmount);
+
+        let stake = &mut ctx.accounts.resolver_stake;
+        require_gte!(stake.staked_amount, amount, EscrowError::InsufficientStake);
+        stake.staked_amount -= amount;
+        stake.pending_unstake_amount = stake.pending_unstake_amount.checked_add(amount).ok_or(EscrowError::Overflow)?;
+        stake.unstake_available_at = Clock::get()?
+            .unix_timestamp
+            .checked_add(ctx.accounts.config.staking.unstake_cooldown as i64)
+            .ok_or(EscrowError::Overflow)?;
+
+        emit!(UnstakeRequested {
+            resolver: stake.resolver,
+            amount,
+            available_at: stake.unstake_available_at,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_stake(ctx: Context<WithdrawStakeAccounts>) -> Result<()> {
+        let stake = &mut ctx.accounts.resolver_stake;
+        let now = Clock::get()?.unix_timestamp;
+        require_gte!(now, stake.unstake_available_at, EscrowError::UnstakeCooldownActive);
+        require_gte!(now, stake.locked_until, EscrowError::StakeLocked);
+        let amount = stake.pending_unstake_amount;
+        require_gt!(amount, 0, EscrowError::ZeroAmount);
+        stake.pending_unstake_amount = 0;
+
+        transfer_lamports_from_pda(&stake.to_account_info(), &ctx.accounts.resolver.to_account_info(), amount)?;
+
+        emit!(StakeWithdrawn {
+            resolver: stake.resolver,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// The resolver commits to filling the destination side of a source order. From then on, letting the
+    /// order run into cancellation through its own fault slashes the resolver's stake, which can't be withdrawn
+    /// before the order reaches public cancellation.
+    pub fn accept_order_source(ctx: Context<AcceptOrderSourceAccounts>, _order_id: [u8; 32]) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_source;
+        order.accept(Clock::get()?.unix_timestamp)?;
+        ctx.accounts
+            .resolver_stake
+            .lock_until(Timelocks::deadline(order.creation_timestamp, order.timelocks.public_cancellation)?);
+
+        emit!(OrderAcceptedSource {
+            order_id: order.order_id,
+            resolver: order.resolver,
+            staked_amount: ctx.accounts.resolver_stake.staked_amount,
+        });
+        Ok(())
+    }
+
+    /// Records on a partial-fill order that one of its secrets was public on Solana before public withdrawal
+    /// opened. The hashlock is a Merkle root, so the secret registry can't be looked up from the order itself;
+    /// anyone holding the leaf proof reports the reveal here, before cancellation checks the resolver's fault.
+    pub fn report_revealed_secret(
+        ctx: Context<ReportRevealedSecretAccounts>,
+        _order_id: [u8; 32],
+        secret_index: u16,
+        merkle_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_source;
+        let secret_record = &ctx.accounts.secret_record;
+        order.report_revealed_secret(secret_record, secret_index, &merkle_proof)?;
+
+        emit!(RevealedSecretReported {
+            order_id: order.order_id,
+            resolver: order.resolver,
+            secret_index,
+            revealed_at: secret_record.revealed_at,
+        });
+        Ok(())
+    }
+
     // --- Source Chain Logic ---
+    // SPL handlers accept both Token and Token-2022 mints. For transfer-hook mints the client appends the hook's
+    // extra account metas as remaining accounts; they are forwarded to every `transfer_checked` CPI.
 
-    pub fn create_order_source(
-        ctx: Context<CreateOrderSourceAccounts>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_order_source<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateOrderSourceAccounts<'info>>,
         order_id: [u8; 32],
         resolver_address: Pubkey,
         // token_mint_on_source: Pubkey, // Already in ctx.accounts.token_mint_source
         amount: u64,
-        hash_secret: [u8; 32],
-        timeout_duration_seconds: i64,
+        hash_secret: [u8; 32], // Merkle root of the secret hashes when parts_amount > 1
+        hash_algorithm: HashAlgorithm, // How secrets are hashed into the hashlock (and Merkle leaves)
+        parts_amount: u16, // 1 = single fill, N > 1 = up to N partial fills backed by N + 1 secrets
+        timelocks: Timelocks,
         safety_deposit_sol: u64,
+        public_cancel_reward_bps: u16, // Share of the safety deposit paid to whoever cancels during public cancellation
+        auction: AuctionDetails, // Minimum the maker accepts on the destination chain over time
+        destination: DestinationDescriptor, // Chain, token and recipient the maker expects to be paid on
+        metadata: Option<DonationMetadata>, // Payment-link donation shown to the creator
+        integrator_fee: Option<IntegratorFee>, // Taken from each fill paid out to the resolver
+        bind_order_id: bool, // Require order_id to be the hash of the order parameters (see `order_id_for`)
     ) -> Result<()> {
-        require_gt!(amount, 0, EscrowError::ZeroAmount);
-        require_gt!(safety_deposit_sol, 0, EscrowError::ZeroAmount);
-
-        let order = &mut ctx.accounts.order_state_source;
-        order.order_id = order_id;
-        order.maker = ctx.accounts.maker.key();
-        order.resolver = resolver_address;
-        order.token_mint_source = ctx.accounts.token_mint_source.key();
-        order.amount = amount;
-        order.safety_deposit_sol = safety_deposit_sol;
-        order.hash_secret = hash_secret;
-        order.creation_timestamp = Clock::get()?.unix_timestamp;
-        order.timeout_duration_seconds = timeout_duration_seconds;
-        order.is_withdrawn = false;
-        order.is_cancelled = false;
-        order.escrow_authority_bump = ctx.bumps.escrow_authority; // Store bump for PDA signing
-
         // Transfer SOL safety deposit from maker to the order_state_source PDA
         let ix = system_instruction::transfer(
             &ctx.accounts.maker.key(),
-            &order.key(),
+            &ctx.accounts.order_state_source.key(),
             safety_deposit_sol,
         );
         anchor_lang::solana_program::program::invoke(
             &ix,
             &[
                 ctx.accounts.maker.to_account_info(),
-                order.to_account_info(),
+                ctx.accounts.order_state_source.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
         )?;
 
-        // Transfer SPL tokens from maker to escrow token account
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.maker_token_account_source.to_account_info(),
-            to: ctx.accounts.escrow_token_account_source.to_account_info(),
-            authority: ctx.accounts.maker.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+        // Transfer tokens from maker to escrow token account, recording what actually arrived after transfer fees
+        let received_amount = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_source,
+            &ctx.accounts.maker_token_account_source,
+            &mut ctx.accounts.escrow_token_account_source,
+            ctx.accounts.maker.to_account_info(),
+            &[],
+            ctx.remaining_accounts,
+            amount,
+        )?;
 
-        emit!(OrderCreatedSource {
+        let terms = SourceOrderTerms {
             order_id,
-            maker: order.maker,
-            resolver: order.resolver,
-            token_mint_source: order.token_mint_source,
-            amount: order.amount,
-            safety_deposit_sol: order.safety_deposit_sol,
-            hash_secret: order.hash_secret,
-            timeout_timestamp: order.creation_timestamp + order.timeout_duration_seconds,
-        });
+            order_id_bound: bind_order_id,
+            resolver: resolver_address,
+            token_mint_source: ctx.accounts.token_mint_source.key(),
+            amount: received_amount,
+            hash_secret,
+            hash_algorithm,
+            parts_amount,
+            timelocks,
+            safety_deposit_sol,
+            public_cancel_reward_bps,
+            auction,
+            destination,
+            metadata,
+            integrator_fee,
+        };
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        terms.validate()?;
+        terms.check_order_id(&ctx.accounts.maker.key())?;
+        ctx.accounts.config.limits.check_source(&terms, current_timestamp)?;
+
+        let order = &mut ctx.accounts.order_state_source;
+        order.open(
+            ctx.accounts.maker.key(),
+            terms,
+            ctx.accounts.config.protocol_fee_bps,
+            current_timestamp,
+            false,
+            ctx.bumps.escrow_authority,
+        );
+        let reservation = order.nullifier(order.key(), OrderOutcome::Reserved, None, 0);
+        reserve_hashlock(&mut ctx.accounts.hashlock_nullifier, reservation, ctx.bumps.hashlock_nullifier);
+
+        emit!(order.created_event());
+
+        Ok(())
+    }
+
+    /// Gasless variant of `create_order_source`: the maker signs an `OrderIntent` off chain (verified through an
+    /// Ed25519 program instruction placed right before this one) and the resolver submits it, paying rent and the
+    /// safety deposit. Tokens are pulled through an SPL delegate approval granted to the order's escrow authority.
+    pub fn create_order_source_with_intent<'info>(ctx: Context<'_, '_, 'info, 'info, CreateOrderSourceWithIntentAccounts<'info>>, intent: OrderIntent) -> Result<()> {
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        require_gte!(intent.expiry, current_timestamp, EscrowError::IntentExpired);
+        verify_ed25519_signature(&ctx.accounts.instructions_sysvar, &intent.maker, &intent.signing_message()?)?;
+
+        let nonce = &mut ctx.accounts.intent_nonce;
+        nonce.maker = intent.maker;
+        nonce.nonce = intent.nonce;
+        nonce.order_id = intent.terms.order_id;
+
+        // Resolver fronts the SOL safety deposit on the maker's behalf
+        let ix = system_instruction::transfer(
+            &ctx.accounts.resolver.key(),
+            &ctx.accounts.order_state_source.key(),
+            intent.terms.safety_deposit_sol,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.resolver.to_account_info(),
+                ctx.accounts.order_state_source.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        // Pull tokens from the maker using the escrow authority's delegate approval
+        let order_key = ctx.accounts.order_state_source.key();
+        let authority_seeds = &[
+            ESCROW_AUTHORITY_SEED_PREFIX,
+            order_key.as_ref(),
+            &[ctx.bumps.escrow_authority],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let received_amount = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_source,
+            &ctx.accounts.maker_token_account_source,
+            &mut ctx.accounts.escrow_token_account_source,
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
+            intent.terms.amount,
+        )?;
+
+        let mut terms = intent.terms;
+        terms.amount = received_amount;
+        terms.validate()?;
+        terms.check_order_id(&intent.maker)?;
+        ctx.accounts.config.limits.check_source(&terms, current_timestamp)?;
+
+        let order = &mut ctx.accounts.order_state_source;
+        order.open(
+            intent.maker,
+            terms,
+            ctx.accounts.config.protocol_fee_bps,
+            current_timestamp,
+            false,
+            ctx.bumps.escrow_authority,
+        );
+        order.rent_payer = ctx.accounts.resolver.key(); // The resolver submits the intent and pays its rent
+        let reservation = order.nullifier(order.key(), OrderOutcome::Reserved, None, 0);
+        reserve_hashlock(&mut ctx.accounts.hashlock_nullifier, reservation, ctx.bumps.hashlock_nullifier);
+
+        emit!(order.created_event());
 
         Ok(())
     }
 
-    pub fn withdraw_source(
-        ctx: Context<WithdrawSourceAccounts>,
+    pub fn withdraw_source<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawSourceAccounts<'info>>,
         _order_id: [u8; 32], // order_id is part of the PDA seed, not strictly needed as arg if using PDA correctly
         secret: [u8; 32],
+        secret_index: u16, // Always 0 for single-fill orders
+        merkle_proof: Vec<[u8; 32]>, // Empty for single-fill orders
+        fill_amount: u64,
     ) -> Result<()> {
         let order = &mut ctx.accounts.order_state_source;
-        require_keys_eq!(ctx.accounts.resolver.key(), order.resolver, EscrowError::CallerNotResolver);
-        
-        let current_timestamp = Clock::get()?.unix_timestamp;
-        require!(current_timestamp < order.creation_timestamp + order.timeout_duration_seconds, EscrowError::TimeoutExpired);
-        require!(!order.is_withdrawn, EscrowError::AlreadyWithdrawn);
-        require!(!order.is_cancelled, EscrowError::AlreadyCancelled);
-
-        let provided_hash = anchor_lang::solana_program::keccak::hash(&secret).to_bytes();
-        require!(provided_hash == order.hash_secret, EscrowError::InvalidSecret);
+        require!(!order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let deposit_release = order.apply_withdrawal(
+            ctx.accounts.caller.key(),
+            &secret,
+            secret_index,
+            &merkle_proof,
+            fill_amount,
+            now,
+        )?;
 
-        order.is_withdrawn = true;
+        // The order closes with its last fill, so that fill, and only that one, writes the nullifiers
+        require!(
+            order.is_withdrawn == ctx.accounts.order_nullifier.is_some()
+                && order.is_withdrawn == ctx.accounts.hashlock_nullifier.is_some(),
+            EscrowError::NullifierMismatch
+        );
+        if let (Some(order_nullifier), Some(hashlock_nullifier)) =
+            (ctx.accounts.order_nullifier.as_mut(), ctx.accounts.hashlock_nullifier.as_mut())
+        {
+            write_nullifiers(
+                order.nullifier(order.key(), OrderOutcome::Withdrawn, Some(secret), now),
+                (order_nullifier, ctx.bumps.order_nullifier.unwrap_or_default()),
+                hashlock_nullifier,
+            );
+        }
+        let hash_algorithm = order.hash_algorithm;
+        ctx.accounts.secret_record.register(hash_algorithm, secret, now, ctx.bumps.secret_record);
 
-        // Transfer SPL tokens from escrow to resolver
-        let order_id_bytes = order.order_id; // order.order_id is already [u8; 32]
+        // Transfer tokens from escrow to resolver
+        let order_key = order.key();
         let authority_seeds = &[
             ESCROW_AUTHORITY_SEED_PREFIX,
-            order_id_bytes.as_ref(),
+            order_key.as_ref(),
             &[order.escrow_authority_bump],
         ];
         let signer_seeds = &[&authority_seeds[..]];
 
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.escrow_token_account_source.to_account_info(),
-            to: ctx.accounts.resolver_token_account_source.to_account_info(),
-            authority: ctx.accounts.escrow_authority.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        token::transfer(cpi_ctx, order.amount)?;
+        let split = split_settlement(fill_amount, order.protocol_fee_bps, order.integrator_fee)?;
+        let received_amount = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_source,
+            &ctx.accounts.escrow_token_account_source,
+            &mut ctx.accounts.resolver_token_account_source,
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
+            split.payout,
+        )?;
+        for (fee_account, fee) in [
+            (ctx.accounts.protocol_fee_token_account.as_mut(), split.protocol_fee),
+            (ctx.accounts.integrator_fee_token_account.as_mut(), split.integrator_fee),
+        ] {
+            pay_fee_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_mint_source,
+                &ctx.accounts.escrow_token_account_source,
+                fee_account,
+                ctx.accounts.escrow_authority.to_account_info(),
+                signer_seeds,
+                ctx.remaining_accounts,
+                fee,
+            )?;
+        }
 
-        // Transfer SOL safety deposit from order_state_source PDA to resolver
+        // Transfer SOL safety deposit share from order_state_source PDA to the caller.
+        // In the public withdrawal window this is the reward for finishing a stuck swap.
         let order_lamports = order.to_account_info().lamports();
-        require_gte!(order_lamports, order.safety_deposit_sol, EscrowError::InsufficientEscrowBalance);
+        require_gte!(order_lamports, deposit_release, EscrowError::InsufficientEscrowBalance);
         
-        **order.to_account_info().try_borrow_mut_lamports()? -= order.safety_deposit_sol;
-        **ctx.accounts.resolver.to_account_info().try_borrow_mut_lamports()? += order.safety_deposit_sol;
+        **order.to_account_info().try_borrow_mut_lamports()? -= deposit_release;
+        **ctx.accounts.caller.to_account_info().try_borrow_mut_lamports()? += deposit_release;
 
-        emit!(OrderWithdrawnSource { order_id: order.order_id, resolver: order.resolver, secret });
-        
-        // Closing accounts will be handled by Anchor if `close = resolver` is set on the PDA account in Accounts struct
+        // Rent of the accounts closed below, returned to whoever paid it at creation
+        let rent_refunded = if order.is_withdrawn {
+            rent_to_refund(&[order.to_account_info(), ctx.accounts.escrow_token_account_source.to_account_info()])?
+        } else {
+            0
+        };
+
+        emit!(OrderWithdrawnSource {
+            order_id: order.order_id,
+            resolver: order.resolver,
+            caller: ctx.accounts.caller.key(),
+            secret,
+            secret_index,
+            fill_amount,
+            received_amount,
+            protocol_fee: split.protocol_fee,
+            integrator_fee: split.integrator_fee,
+            remaining_amount: order.remaining_amount,
+            deposit_released: deposit_release,
+            rent_payer: order.rent_payer,
+            rent_refunded,
+        });
+
+        // Once fully filled, close the escrow token account and the order, returning rent to its payer
+        if order.is_withdrawn {
+            close_escrow_token_account(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_mint_source,
+                &ctx.accounts.escrow_token_account_source,
+                ctx.accounts.rent_payer.to_account_info(),
+                ctx.accounts.escrow_authority.to_account_info(),
+                signer_seeds,
+            )?;
+            order.close(ctx.accounts.rent_payer.to_account_info())?;
+        }
         Ok(())
     }
 
-    pub fn cancel_source(ctx: Context<CancelSourceAccounts>, _order_id: [u8; 32]) -> Result<()> {
+    pub fn cancel_source<'info>(ctx: Context<'_, '_, 'info, 'info, CancelSourceAccounts<'info>>, _order_id: [u8; 32]) -> Result<()> {
         let order = &mut ctx.accounts.order_state_source;
-        
-        let current_timestamp = Clock::get()?.unix_timestamp;
-        require!(current_timestamp >= order.creation_timestamp + order.timeout_duration_seconds, EscrowError::TimeoutNotExpired);
-        require!(!order.is_withdrawn, EscrowError::AlreadyWithdrawn);
-        require!(!order.is_cancelled, EscrowError::AlreadyCancelled);
+        require!(!order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let (deposit_refund, keeper_reward) = order.apply_cancellation(ctx.accounts.canceller.key(), now)?;
 
-        order.is_cancelled = true;
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Cancelled, None, now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
 
-        // Transfer SPL tokens from escrow back to maker
-        let order_id_bytes = order.order_id;
+        // Transfer tokens from escrow back to maker
+        let order_key = order.key();
         let authority_seeds = &[
             ESCROW_AUTHORITY_SEED_PREFIX,
-            order_id_bytes.as_ref(),
+            order_key.as_ref(),
             &[order.escrow_authority_bump],
         ];
         let signer_seeds = &[&authority_seeds[..]];
         
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.escrow_token_account_source.to_account_info(),
-            to: ctx.accounts.maker_token_account_source.to_account_info(),
-            authority: ctx.accounts.escrow_authority.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        token::transfer(cpi_ctx, order.amount)?;
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_source,
+            &ctx.accounts.escrow_token_account_source,
+            &mut ctx.accounts.maker_token_account_source,
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
+            order.remaining_amount,
+        )?;
+        let escrow_rent = ctx.accounts.escrow_token_account_source.to_account_info().lamports();
+        close_escrow_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_source,
+            &ctx.accounts.escrow_token_account_source,
+            ctx.accounts.rent_payer.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+        )?;
 
-        // Transfer SOL safety deposit from order_state_source PDA back to maker
+        // Split the unreleased SOL safety deposit between the keeper (public cancellation only) and whoever posted
+        // it: the maker, or the resolver that submitted an intent
         let order_lamports = order.to_account_info().lamports();
-        require_gte!(order_lamports, order.safety_deposit_sol, EscrowError::InsufficientEscrowBalance);
+        require_gte!(order_lamports, deposit_refund, EscrowError::InsufficientEscrowBalance);
 
-        **order.to_account_info().try_borrow_mut_lamports()? -= order.safety_deposit_sol;
-        **ctx.accounts.maker.to_account_info().try_borrow_mut_lamports()? += order.safety_deposit_sol;
+        **order.to_account_info().try_borrow_mut_lamports()? -= deposit_refund;
+        **ctx.accounts.canceller.to_account_info().try_borrow_mut_lamports()? += keeper_reward;
+        **ctx.accounts.rent_payer.to_account_info().try_borrow_mut_lamports()? += deposit_refund - keeper_reward;
 
-        emit!(OrderCancelledSource { order_id: order.order_id, canceller: ctx.accounts.canceller.key() });
-        
-        // Closing accounts handled by Anchor via `close = maker`
+        slash_resolver(
+            order,
+            &ctx.accounts.config.staking,
+            &ctx.accounts.secret_record.to_account_info(),
+            &ctx.accounts.destination_hashlock_nullifier.to_account_info(),
+            ctx.accounts.resolver_stake.as_mut(),
+            ctx.accounts.treasury.as_ref(),
+            &ctx.accounts.maker.to_account_info(),
+        )?;
+
+        emit!(OrderCancelledSource {
+            order_id: order.order_id,
+            canceller: ctx.accounts.canceller.key(),
+            refunded_amount: order.remaining_amount,
+            keeper_reward,
+            deposit_refunded: deposit_refund,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order.to_account_info()])?.checked_add(escrow_rent).ok_or(EscrowError::Overflow)?,
+        });
+
+        // Closing the order account handled by Anchor via `close = rent_payer`
         Ok(())
     }
 
     // --- Destination Chain Logic ---
 
-    pub fn fill_order_destination(
-        ctx: Context<FillOrderDestinationAccounts>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_order_destination<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FillOrderDestinationAccounts<'info>>,
         order_id: [u8; 32], // Must match source order_id
-        maker_on_source: Pubkey,
+        maker_on_source: Pubkey, // Solana maker, or the payout recipient when maker_evm_address is set
+        maker_evm_address: Option<[u8; 20]>, // Set when the maker only holds an Ethereum address
         // token_mint_on_destination: Pubkey, // Already in ctx.accounts
-        amount_on_destination: u64,
+        amount_on_destination: u64, // Sent by the resolver; the amount escrowed after transfer fees must meet the auction curve
         hash_secret: [u8; 32], // Must match source hash_secret
-        timeout_duration_seconds: i64,
+        hash_algorithm: HashAlgorithm, // Must match source hash_algorithm
+        timelocks: Timelocks,
+        source: SourceCommitment, // Source escrow this fill mirrors
+        source_cancellation_timestamp: i64, // When the source order stops accepting withdrawals
         safety_deposit_sol: u64,
+        public_cancel_reward_bps: u16,
+        auction: AuctionDetails, // Must match the curve committed in the source order
+        destination: DestinationDescriptor, // The source order's descriptor, which this fill must satisfy
+        metadata: Option<DonationMetadata>, // Copied from the source order so destination events carry it
+        integrator_fee: Option<IntegratorFee>, // The descriptor's fee, taken from the amount paid out to the maker
     ) -> Result<()> {
-        require_gt!(amount_on_destination, 0, EscrowError::ZeroAmount);
-        require_gt!(safety_deposit_sol, 0, EscrowError::ZeroAmount);
-
-        let order = &mut ctx.accounts.order_state_destination;
-        order.order_id = order_id;
-        order.maker_on_source = maker_on_source;
-        order.resolver = ctx.accounts.resolver.key();
-        order.token_mint_destination = ctx.accounts.token_mint_destination.key();
-        order.amount_on_destination = amount_on_destination;
-        order.safety_deposit_sol = safety_deposit_sol;
-        order.hash_secret = hash_secret;
-        order.creation_timestamp = Clock::get()?.unix_timestamp; // Timestamp when filled on dest
-        order.timeout_duration_seconds = timeout_duration_seconds;
-        order.is_withdrawn_by_maker = false;
-        order.is_cancelled_by_resolver = false;
-        order.escrow_authority_bump = ctx.bumps.escrow_authority;
-
         // Transfer SOL safety deposit from resolver to the order_state_destination PDA
         let ix = system_instruction::transfer(
             &ctx.accounts.resolver.key(),
-            &order.key(),
+            &ctx.accounts.order_state_destination.key(),
             safety_deposit_sol,
         );
         anchor_lang::solana_program::program::invoke(
             &ix,
             &[
                 ctx.accounts.resolver.to_account_info(),
-                order.to_account_info(),
+                ctx.accounts.order_state_destination.to_account_info(),
                 ctx.accounts.system_program.to_account_info(),
             ],
         )?;
 
-        // Transfer SPL tokens from resolver to escrow token account
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.resolver_token_account_destination.to_account_info(),
-            to: ctx.accounts.escrow_token_account_destination.to_account_info(),
-            authority: ctx.accounts.resolver.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, amount_on_destination)?;
+        // Transfer tokens from resolver to escrow token account, recording what actually arrived after transfer fees
+        let received_amount = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.resolver_token_account_destination,
+            &mut ctx.accounts.escrow_token_account_destination,
+            ctx.accounts.resolver.to_account_info(),
+            &[],
+            ctx.remaining_accounts,
+            amount_on_destination,
+        )?;
 
-        emit!(OrderFilledDestination {
+        let terms = DestinationOrderTerms {
             order_id,
+            order_id_bound: order_id_bound_for(ctx.accounts.source_order.as_ref()),
             maker_on_source,
-            resolver: order.resolver,
-            token_mint_destination: order.token_mint_destination,
-            amount_on_destination: order.amount_on_destination,
-            safety_deposit_sol: order.safety_deposit_sol,
-            hash_secret: order.hash_secret,
-            timeout_timestamp: order.creation_timestamp + order.timeout_duration_seconds,
-        });
+            maker_evm_address,
+            token_mint_destination: ctx.accounts.token_mint_destination.key(),
+            amount_on_destination: received_amount,
+            hash_secret,
+            hash_algorithm,
+            timelocks,
+            source,
+            source_cancellation_timestamp,
+            safety_deposit_sol,
+            public_cancel_reward_bps,
+            auction,
+            destination,
+            metadata,
+            integrator_fee,
+        };
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        terms.validate(current_timestamp)?;
+        terms.check_descriptor(ctx.accounts.token_mint_destination.decimals)?;
+        terms.check_source_order(ctx.accounts.source_order.as_ref())?;
+        terms.check_order_id()?;
+        ctx.accounts.config.limits.check_destination(&terms, current_timestamp)?;
+
+        let order = &mut ctx.accounts.order_state_destination;
+        order.open(
+            ctx.accounts.resolver.key(),
+            terms,
+            ctx.accounts.config.protocol_fee_bps,
+            current_timestamp,
+            false,
+            ctx.bumps.escrow_authority,
+        );
+        let reservation = order.nullifier(order.key(), OrderOutcome::Reserved, None, 0);
+        reserve_hashlock(&mut ctx.accounts.hashlock_nullifier, reservation, ctx.bumps.hashlock_nullifier);
+
+        emit!(order.filled_event());
         Ok(())
     }
 
-    pub fn withdraw_destination(
-        ctx: Context<WithdrawDestinationAccounts>,
+    pub fn withdraw_destination<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawDestinationAccounts<'info>>,
         _order_id: [u8; 32],
-        secret: [u8; 32],
+        secret: Option<[u8; 32]>, // None to use the secret already in the registry
     ) -> Result<()> {
         let order = &mut ctx.accounts.order_state_destination;
-        // Note: Maker on source is the one withdrawing here.
-        // The `maker_on_destination_chain_signer` is the one initiating this transaction.
-        // We need a way to verify this signer corresponds to `order.maker_on_source`.
-        // This might require an off-chain signature verification or a trusted relayer model
-        // if `maker_on_source` is an ETH address. For now, assume `maker_on_destination_chain_signer` IS the maker.
-        require_keys_eq!(ctx.accounts.maker_on_destination_chain_signer.key(), order.maker_on_source, EscrowError::CallerNotMaker);
-
-        let current_timestamp = Clock::get()?.unix_timestamp;
-        require!(current_timestamp < order.creation_timestamp + order.timeout_duration_seconds, EscrowError::TimeoutExpired);
-        require!(!order.is_withdrawn_by_maker, EscrowError::AlreadyWithdrawnByMaker);
-        require!(!order.is_cancelled_by_resolver, EscrowError::AlreadyCancelledByResolver);
-
-        let provided_hash = anchor_lang::solana_program::keccak::hash(&secret).to_bytes();
-        require!(provided_hash == order.hash_secret, EscrowError::InvalidSecret);
-
-        order.is_withdrawn_by_maker = true;
+        require!(!order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let secret = ctx.accounts.secret_record.resolve(secret)?;
+        let order_key = order.key();
+        order.apply_withdrawal(
+            order_key,
+            ctx.accounts.caller.key(),
+            ctx.accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref()),
+            &secret,
+            now,
+        )?;
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Withdrawn, Some(secret), now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
+        let hash_algorithm = order.hash_algorithm;
+        ctx.accounts.secret_record.register(hash_algorithm, secret, now, ctx.bumps.secret_record);
 
-        // Transfer SPL tokens from escrow to maker
-        let order_id_bytes = order.order_id;
+        // Transfer tokens from escrow to maker
+        let order_key = order.key();
         let authority_seeds = &[
             ESCROW_AUTHORITY_SEED_PREFIX,
-            order_id_bytes.as_ref(),
+            order_key.as_ref(),
             &[order.escrow_authority_bump],
         ];
         let signer_seeds = &[&authority_seeds[..]];
 
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.escrow_token_account_destination.to_account_info(),
-            to: ctx.accounts.maker_token_account_destination.to_account_info(),
-            authority: ctx.accounts.escrow_authority.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        token::transfer(cpi_ctx, order.amount_on_destination)?;
+        let split = split_settlement(order.amount_on_destination, order.protocol_fee_bps, order.integrator_fee)?;
+        let received_amount = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            &mut ctx.accounts.maker_token_account_destination,
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
+            split.payout,
+        )?;
+        for (fee_account, fee) in [
+            (ctx.accounts.protocol_fee_token_account.as_mut(), split.protocol_fee),
+            (ctx.accounts.integrator_fee_token_account.as_mut(), split.integrator_fee),
+        ] {
+            pay_fee_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_mint_destination,
+                &ctx.accounts.escrow_token_account_destination,
+                fee_account,
+                ctx.accounts.escrow_authority.to_account_info(),
+                signer_seeds,
+                ctx.remaining_accounts,
+                fee,
+            )?;
+        }
+        let escrow_rent = ctx.accounts.escrow_token_account_destination.to_account_info().lamports();
+        close_escrow_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            ctx.accounts.rent_payer.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+        )?;
 
-        // Transfer SOL safety deposit from order_state_destination PDA to maker
+        // Transfer SOL safety deposit from order_state_destination PDA to the caller
+        // (the maker itself, or whoever finished the swap in the public withdrawal window)
         let order_lamports = order.to_account_info().lamports();
         require_gte!(order_lamports, order.safety_deposit_sol, EscrowError::InsufficientEscrowBalance);
         
         **order.to_account_info().try_borrow_mut_lamports()? -= order.safety_deposit_sol;
-        **ctx.accounts.maker_on_destination_chain_signer.to_account_info().try_borrow_mut_lamports()? += order.safety_deposit_sol;
+        **ctx.accounts.caller.to_account_info().try_borrow_mut_lamports()? += order.safety_deposit_sol;
 
-        emit!(MakerWithdrawnDestination { order_id: order.order_id, maker: order.maker_on_source, secret });
+        emit!(MakerWithdrawnDestination {
+            order_id: order.order_id,
+            maker: order.maker_on_source,
+            maker_evm_address: order.maker_evm_address,
+            caller: ctx.accounts.caller.key(),
+            secret,
+            received_amount,
+            protocol_fee: split.protocol_fee,
+            integrator_fee: split.integrator_fee,
+            deposit_released: order.safety_deposit_sol,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order.to_account_info()])?.checked_add(escrow_rent).ok_or(EscrowError::Overflow)?,
+            relayer_reward: 0,
+            metadata: order.metadata.clone(),
+        });
         Ok(())
     }
 
-    pub fn cancel_destination(ctx: Context<CancelDestinationAccounts>, _order_id: [u8; 32]) -> Result<()> {
+    /// Finalizes a destination order for the maker: anyone holding the secret may submit it, at any point of
+    /// the withdrawal window, because the tokens can only reach the maker's associated token account.
+    pub fn relay_withdraw_destination<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RelayWithdrawDestinationAccounts<'info>>,
+        _order_id: [u8; 32],
+        secret: Option<[u8; 32]>, // None to use the secret already in the registry
+    ) -> Result<()> {
         let order = &mut ctx.accounts.order_state_destination;
-        require_keys_eq!(ctx.accounts.resolver.key(), order.resolver, EscrowError::CallerNotResolver);
+        require!(!order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let secret = ctx.accounts.secret_record.resolve(secret)?;
+        order.apply_relayed_withdrawal(&secret, now)?;
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Withdrawn, Some(secret), now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
+        let hash_algorithm = order.hash_algorithm;
+        ctx.accounts.secret_record.register(hash_algorithm, secret, now, ctx.bumps.secret_record);
 
-        let current_timestamp = Clock::get()?.unix_timestamp;
-        require!(current_timestamp >= order.creation_timestamp + order.timeout_duration_seconds, EscrowError::TimeoutNotExpired);
-        require!(!order.is_withdrawn_by_maker, EscrowError::AlreadyWithdrawnByMaker);
-        require!(!order.is_cancelled_by_resolver, EscrowError::AlreadyCancelledByResolver);
+        // Transfer tokens from escrow to the maker's associated token account
+        let order_key = order.key();
+        let authority_seeds = &[
+            ESCROW_AUTHORITY_SEED_PREFIX,
+            order_key.as_ref(),
+            &[order.escrow_authority_bump],
+        ];
+        let signer_seeds = &[&authority_seeds[..]];
+
+        let split = split_settlement(order.amount_on_destination, order.protocol_fee_bps, order.integrator_fee)?;
+        let received_amount = transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            &mut ctx.accounts.maker_token_account_destination,
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
+            split.payout,
+        )?;
+        for (fee_account, fee) in [
+            (ctx.accounts.protocol_fee_token_account.as_mut(), split.protocol_fee),
+            (ctx.accounts.integrator_fee_token_account.as_mut(), split.integrator_fee),
+        ] {
+            pay_fee_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_mint_destination,
+                &ctx.accounts.escrow_token_account_destination,
+                fee_account,
+                ctx.accounts.escrow_authority.to_account_info(),
+                signer_seeds,
+                ctx.remaining_accounts,
+                fee,
+            )?;
+        }
+        let escrow_rent = ctx.accounts.escrow_token_account_destination.to_account_info().lamports();
+        close_escrow_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            ctx.accounts.rent_payer.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+        )?;
+
+        // The relayer's share of the safety deposit pays for submitting; the rest goes to the maker
+        let relayer_reward = bps_share(order.safety_deposit_sol, ctx.accounts.config.relayer_reward_bps)?;
+        let order_info = order.to_account_info();
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.relayer.to_account_info(), relayer_reward)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.maker.to_account_info(), order.safety_deposit_sol - relayer_reward)?;
+
+        emit!(MakerWithdrawnDestination {
+            order_id: order.order_id,
+            maker: order.maker_on_source,
+            maker_evm_address: order.maker_evm_address,
+            caller: ctx.accounts.relayer.key(),
+            secret,
+            received_amount,
+            protocol_fee: split.protocol_fee,
+            integrator_fee: split.integrator_fee,
+            deposit_released: order.safety_deposit_sol,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order.to_account_info()])?.checked_add(escrow_rent).ok_or(EscrowError::Overflow)?,
+            relayer_reward,
+            metadata: order.metadata.clone(),
+        });
+        Ok(())
+    }
+
+    pub fn cancel_destination<'info>(ctx: Context<'_, '_, 'info, 'info, CancelDestinationAccounts<'info>>, _order_id: [u8; 32]) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_destination;
+        require!(!order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let keeper_reward = order.apply_cancellation(ctx.accounts.canceller.key(), now)?;
 
-        order.is_cancelled_by_resolver = true;
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Cancelled, None, now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
 
-        // Transfer SPL tokens from escrow back to resolver
-        let order_id_bytes = order.order_id;
+        // Transfer tokens from escrow back to resolver
+        let order_key = order.key();
         let authority_seeds = &[
             ESCROW_AUTHORITY_SEED_PREFIX,
-            order_id_bytes.as_ref(),
+            order_key.as_ref(),
             &[order.escrow_authority_bump],
         ];
         let signer_seeds = &[&authority_seeds[..]];
 
-        let cpi_accounts = SplTransfer {
-            from: ctx.accounts.escrow_token_account_destination.to_account_info(),
-            to: ctx.accounts.resolver_token_account_destination.to_account_info(),
-            authority: ctx.accounts.escrow_authority.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        token::transfer(cpi_ctx, order.amount_on_destination)?;
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            &mut ctx.accounts.resolver_token_account_destination,
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+            ctx.remaining_accounts,
+            order.amount_on_destination,
+        )?;
+        let escrow_rent = ctx.accounts.escrow_token_account_destination.to_account_info().lamports();
+        close_escrow_token_account(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            ctx.accounts.rent_payer.to_account_info(),
+            ctx.accounts.escrow_authority.to_account_info(),
+            signer_seeds,
+        )?;
 
-        // Transfer SOL safety deposit from order_state_destination PDA back to resolver
+        // Split the SOL safety deposit between the keeper (public cancellation only) and the resolver
         let order_lamports = order.to_account_info().lamports();
         require_gte!(order_lamports, order.safety_deposit_sol, EscrowError::InsufficientEscrowBalance);
 
         **order.to_account_info().try_borrow_mut_lamports()? -= order.safety_deposit_sol;
-        **ctx.accounts.resolver.to_account_info().try_borrow_mut_lamports()? += order.safety_deposit_sol;
+        **ctx.accounts.canceller.to_account_info().try_borrow_mut_lamports()? += keeper_reward;
+        **ctx.accounts.resolver.to_account_info().try_borrow_mut_lamports()? += order.safety_deposit_sol - keeper_reward;
 
-        emit!(OrderCancelledByResolverDestination { order_id: order.order_id, resolver: order.resolver });
+        emit!(OrderCancelledByResolverDestination {
+            order_id: order.order_id,
+            resolver: order.resolver,
+            canceller: ctx.accounts.canceller.key(),
+            keeper_reward,
+            deposit_refunded: order.safety_deposit_sol,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order.to_account_info()])?.checked_add(escrow_rent).ok_or(EscrowError::Overflow)?,
+        });
         Ok(())
     }
-}
 
-// --- Account Structs ---
+    // --- Secret Registry ---
 
-#[derive(Accounts)]
-#[instruction(order_id: [u8; 32])]
-pub struct CreateOrderSourceAccounts<'info> {
-    #[account(
-        init,
-        payer = maker,
-        space = OrderStateSource::LEN,
-        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_id.as_ref()],
-        bump
-    )]
-    pub order_state_source: Account<'info, OrderStateSource>,
+    /// Returns the preimage registered for `hashlock`, for programs that consume it through CPI.
+    pub fn registered_secret(ctx: Context<ReadSecretAccounts>, _hashlock: [u8; 32]) -> Result<[u8; 32]> {
+        Ok(ctx.accounts.secret_record.secret)
+    }
 
-    #[account(mut)]
-    pub maker: Signer<'info>,
+    /// Registers a preimage without a withdrawal, e.g. a secret the maker revealed on another chain, so that
+    /// it can be used to withdraw on Solana and counts as public when a source order is cancelled.
+    pub fn publish_secret(ctx: Context<PublishSecretAccounts>, hash_algorithm: HashAlgorithm, secret: [u8; 32]) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.secret_record.register(hash_algorithm, secret, now, ctx.bumps.secret_record);
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub maker_token_account_source: Account<'info, TokenAccount>,
+    // --- Same-Chain Settlement ---
 
-    #[account(
-        init,
-        payer = maker,
-        token::mint = token_mint_source,
-        token::authority = escrow_authority, // PDA is authority
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref(), b"token_source"], // Unique seed for this token account
-        bump
-    )]
-    pub escrow_token_account_source: Account<'info, TokenAccount>,
-    
-    /// CHECK: This PDA acts as the authority for the escrow_token_account_source.
-    #[account(
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref()],
-        bump
+    /// Settles a Solana-to-Solana swap in one instruction: with the secret, the resolver releases the source escrow
+    /// to itself and the destination escrow to the maker together, so neither leg waits for a finality lock or a
+    /// second HTLC round. Both orders must be single-fill SPL orders of the same resolver, the destination one
+    /// having been filled against this source order (see `check_source_order`). Transfer-hook accounts for either
+    /// mint go in the remaining accounts and are forwarded to both legs.
+    pub fn atomic_settle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AtomicSettleAccounts<'info>>,
+        _order_id: [u8; 32],
+        secret: [u8; 32],
+    ) -> Result<()> {
+        let source = &mut ctx.accounts.order_state_source;
+        let destination = &mut ctx.accounts.order_state_destination;
+        require!(!source.is_native && !destination.is_native, EscrowError::OrderKindMismatch);
+        require!(destination.mirrors(source), EscrowError::SourceOrderMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let source_deposit = source.apply_atomic_settlement(&secret, now)?;
+        destination.apply_atomic_settlement(&secret, now)?;
+
+        write_nullifiers(
+            source.nullifier(source.key(), OrderOutcome::Withdrawn, Some(secret), now),
+            (&mut ctx.accounts.source_order_nullifier, ctx.bumps.source_order_nullifier),
+            &mut ctx.accounts.source_hashlock_nullifier,
+        );
+        write_nullifiers(
+            destination.nullifier(destination.key(), OrderOutcome::Withdrawn, Some(secret), now),
+            (&mut ctx.accounts.destination_order_nullifier, ctx.bumps.destination_order_nullifier),
+            &mut ctx.accounts.destination_hashlock_nullifier,
+        );
+        ctx.accounts.secret_record.register(source.hash_algorithm, secret, now, ctx.bumps.secret_record);
+
+        // Source leg: escrowed tokens to the resolver
+        let source_key = source.key();
+        let source_authority_seeds = &[
+            ESCROW_AUTHORITY_SEED_PREFIX,
+            source_key.as_ref(),
+            &[source.escrow_authority_bump],
+        ];
+        let source_signer_seeds = &[&source_authority_seeds[..]];
+
+        let source_split = split_settlement(source.amount, source.protocol_fee_bps, source.integrator_fee)?;
+        let source_received = transfer_tokens(
+            &ctx.accounts.token_program_source,
+            &ctx.accounts.token_mint_source,
+            &ctx.accounts.escrow_token_account_source,
+            &mut ctx.accounts.resolver_token_account_source,
+            ctx.accounts.escrow_authority_source.to_account_info(),
+            source_signer_seeds,
+            ctx.remaining_accounts,
+            source_split.payout,
+        )?;
+        for (fee_account, fee) in [
+            (ctx.accounts.protocol_fee_token_account_source.as_deref_mut(), source_split.protocol_fee),
+            (ctx.accounts.integrator_fee_token_account_source.as_deref_mut(), source_split.integrator_fee),
+        ] {
+            pay_fee_tokens(
+                &ctx.accounts.token_program_source,
+                &ctx.accounts.token_mint_source,
+                &ctx.accounts.escrow_token_account_source,
+                fee_account,
+                ctx.accounts.escrow_authority_source.to_account_info(),
+                source_signer_seeds,
+                ctx.remaining_accounts,
+                fee,
+            )?;
+        }
+        let source_escrow_rent = ctx.accounts.escrow_token_account_source.to_account_info().lamports();
+        close_escrow_token_account(
+            &ctx.accounts.token_program_source,
+            &ctx.accounts.token_mint_source,
+            &ctx.accounts.escrow_token_account_source,
+            ctx.accounts.source_rent_payer.to_account_info(),
+            ctx.accounts.escrow_authority_source.to_account_info(),
+            source_signer_seeds,
+        )?;
+
+        // Destination leg: escrowed tokens to the maker
+        let destination_key = destination.key();
+        let destination_authority_seeds = &[
+            ESCROW_AUTHORITY_SEED_PREFIX,
+            destination_key.as_ref(),
+            &[destination.escrow_authority_bump],
+        ];
+        let destination_signer_seeds = &[&destination_authority_seeds[..]];
+
+        let destination_split =
+            split_settlement(destination.amount_on_destination, destination.protocol_fee_bps, destination.integrator_fee)?;
+        let destination_received = transfer_tokens(
+            &ctx.accounts.token_program_destination,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            &mut ctx.accounts.maker_token_account_destination,
+            ctx.accounts.escrow_authority_destination.to_account_info(),
+            destination_signer_seeds,
+            ctx.remaining_accounts,
+            destination_split.payout,
+        )?;
+        for (fee_account, fee) in [
+            (ctx.accounts.protocol_fee_token_account_destination.as_deref_mut(), destination_split.protocol_fee),
+            (ctx.accounts.integrator_fee_token_account_destination.as_deref_mut(), destination_split.integrator_fee),
+        ] {
+            pay_fee_tokens(
+                &ctx.accounts.token_program_destination,
+                &ctx.accounts.token_mint_destination,
+                &ctx.accounts.escrow_token_account_destination,
+                fee_account,
+                ctx.accounts.escrow_authority_destination.to_account_info(),
+                destination_signer_seeds,
+                ctx.remaining_accounts,
+                fee,
+            )?;
+        }
+        let destination_escrow_rent = ctx.accounts.escrow_token_account_destination.to_account_info().lamports();
+        close_escrow_token_account(
+            &ctx.accounts.token_program_destination,
+            &ctx.accounts.token_mint_destination,
+            &ctx.accounts.escrow_token_account_destination,
+            ctx.accounts.destination_rent_payer.to_account_info(),
+            ctx.accounts.escrow_authority_destination.to_account_info(),
+            destination_signer_seeds,
+        )?;
+
+        // Each safety deposit goes back to whoever posted it, which is also who paid its order's rent: the maker
+        // (or an intent-submitting resolver) on the source leg, the filling resolver on the destination leg
+        let source_rent_payer = ctx.accounts.source_rent_payer.to_account_info();
+        let destination_rent_payer = ctx.accounts.destination_rent_payer.to_account_info();
+        transfer_lamports_from_pda(&source.to_account_info(), &source_rent_payer, source_deposit)?;
+        transfer_lamports_from_pda(&destination.to_account_info(), &destination_rent_payer, destination.safety_deposit_sol)?;
+
+        emit!(OrderWithdrawnSource {
+            order_id: source.order_id,
+            resolver: source.resolver,
+            caller: ctx.accounts.resolver.key(),
+            secret,
+            secret_index: 0,
+            fill_amount: source.amount,
+            received_amount: source_received,
+            protocol_fee: source_split.protocol_fee,
+            integrator_fee: source_split.integrator_fee,
+            remaining_amount: 0,
+            deposit_released: source_deposit,
+            rent_payer: source.rent_payer,
+            rent_refunded: rent_to_refund(&[source.to_account_info()])?.checked_add(source_escrow_rent).ok_or(EscrowError::Overflow)?,
+        });
+        emit!(MakerWithdrawnDestination {
+            order_id: destination.order_id,
+            maker: destination.maker_on_source,
+            maker_evm_address: destination.maker_evm_address,
+            caller: ctx.accounts.resolver.key(),
+            secret,
+            received_amount: destination_received,
+            protocol_fee: destination_split.protocol_fee,
+            integrator_fee: destination_split.integrator_fee,
+            deposit_released: destination.safety_deposit_sol,
+            rent_payer: destination.rent_payer,
+            rent_refunded: rent_to_refund(&[destination.to_account_info()])?
+                .checked_add(destination_escrow_rent)
+                .ok_or(EscrowError::Overflow)?,
+            relayer_reward: 0,
+            metadata: destination.metadata.clone(),
+        });
+        Ok(())
+    }
+
+    // --- Native SOL Escrow ---
+    // Same order lifecycle as above, but the escrowed amount is held as lamports in the order PDA itself,
+    // on top of its rent-exempt reserve and separately from the safety deposit.
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_order_source_native(
+        ctx: Context<CreateOrderSourceNativeAccounts>,
+        order_id: [u8; 32],
+        resolver_address: Pubkey,
+        amount: u64, // Lamports
+        hash_secret: [u8; 32],
+        hash_algorithm: HashAlgorithm,
+        parts_amount: u16,
+        timelocks: Timelocks,
+        safety_deposit_sol: u64,
+        public_cancel_reward_bps: u16,
+        auction: AuctionDetails,
+        destination: DestinationDescriptor,
+        metadata: Option<DonationMetadata>,
+        integrator_fee: Option<IntegratorFee>,
+        bind_order_id: bool,
+    ) -> Result<()> {
+        let terms = SourceOrderTerms {
+            order_id,
+            order_id_bound: bind_order_id,
+            resolver: resolver_address,
+            token_mint_source: Pubkey::default(),
+            amount,
+            hash_secret,
+            hash_algorithm,
+            parts_amount,
+            timelocks,
+            safety_deposit_sol,
+            public_cancel_reward_bps,
+            auction,
+            destination,
+            metadata,
+            integrator_fee,
+        };
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        terms.validate()?;
+        terms.check_order_id(&ctx.accounts.maker.key())?;
+        ctx.accounts.config.limits.check_source(&terms, current_timestamp)?;
+
+        let order = &mut ctx.accounts.order_state_source;
+        order.open(ctx.accounts.maker.key(), terms, ctx.accounts.config.protocol_fee_bps, current_timestamp, true, 0);
+        let reservation = order.nullifier(order.key(), OrderOutcome::Reserved, None, 0);
+        reserve_hashlock(&mut ctx.accounts.hashlock_nullifier, reservation, ctx.bumps.hashlock_nullifier);
+
+        // Transfer the escrowed SOL and the safety deposit from maker to the order_state_source PDA
+        let ix = system_instruction::transfer(
+            &ctx.accounts.maker.key(),
+            &order.key(),
+            amount.checked_add(safety_deposit_sol).ok_or(EscrowError::Overflow)?,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.maker.to_account_info(),
+                order.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        emit!(order.created_event());
+
+        Ok(())
+    }
+
+    pub fn withdraw_source_native(
+        ctx: Context<WithdrawSourceNativeAccounts>,
+        _order_id: [u8; 32],
+        secret: [u8; 32],
+        secret_index: u16,
+        merkle_proof: Vec<[u8; 32]>,
+        fill_amount: u64,
+    ) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_source;
+        require!(order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let deposit_release = order.apply_withdrawal(
+            ctx.accounts.caller.key(),
+            &secret,
+            secret_index,
+            &merkle_proof,
+            fill_amount,
+            now,
+        )?;
+
+        // The order closes with its last fill, so that fill, and only that one, writes the nullifiers
+        require!(
+            order.is_withdrawn == ctx.accounts.order_nullifier.is_some()
+                && order.is_withdrawn == ctx.accounts.hashlock_nullifier.is_some(),
+            EscrowError::NullifierMismatch
+        );
+        if let (Some(order_nullifier), Some(hashlock_nullifier)) =
+            (ctx.accounts.order_nullifier.as_mut(), ctx.accounts.hashlock_nullifier.as_mut())
+        {
+            write_nullifiers(
+                order.nullifier(order.key(), OrderOutcome::Withdrawn, Some(secret), now),
+                (order_nullifier, ctx.bumps.order_nullifier.unwrap_or_default()),
+                hashlock_nullifier,
+            );
+        }
+        let hash_algorithm = order.hash_algorithm;
+        ctx.accounts.secret_record.register(hash_algorithm, secret, now, ctx.bumps.secret_record);
+
+        // Escrowed SOL goes to the resolver net of fees, the safety deposit share to the caller
+        let split = split_settlement(fill_amount, order.protocol_fee_bps, order.integrator_fee)?;
+        let order_info = order.to_account_info();
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.resolver.to_account_info(), split.payout)?;
+        pay_fee_lamports(&order_info, ctx.accounts.treasury.as_ref(), split.protocol_fee)?;
+        pay_fee_lamports(&order_info, ctx.accounts.integrator.as_ref(), split.integrator_fee)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.caller.to_account_info(), deposit_release)?;
+
+        emit!(OrderWithdrawnSource {
+            order_id: order.order_id,
+            resolver: order.resolver,
+            caller: ctx.accounts.caller.key(),
+            secret,
+            secret_index,
+            fill_amount,
+            received_amount: split.payout,
+            protocol_fee: split.protocol_fee,
+            integrator_fee: split.integrator_fee,
+            remaining_amount: order.remaining_amount,
+            deposit_released: deposit_release,
+            rent_payer: order.rent_payer,
+            rent_refunded: if order.is_withdrawn { rent_to_refund(&[order_info])? } else { 0 },
+        });
+
+        // Once fully filled, close the order, returning its rent to whoever paid it
+        if order.is_withdrawn {
+            order.close(ctx.accounts.rent_payer.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    pub fn cancel_source_native(ctx: Context<CancelSourceNativeAccounts>, _order_id: [u8; 32]) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_source;
+        require!(order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let (deposit_refund, keeper_reward) = order.apply_cancellation(ctx.accounts.canceller.key(), now)?;
+
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Cancelled, None, now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
+
+        // Refund the unfilled SOL to the maker and the rest of the deposit, minus the keeper reward, to its poster
+        let order_info = order.to_account_info();
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.canceller.to_account_info(), keeper_reward)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.maker.to_account_info(), order.remaining_amount)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.rent_payer.to_account_info(), deposit_refund - keeper_reward)?;
+
+        slash_resolver(
+            order,
+            &ctx.accounts.config.staking,
+            &ctx.accounts.secret_record.to_account_info(),
+            &ctx.accounts.destination_hashlock_nullifier.to_account_info(),
+            ctx.accounts.resolver_stake.as_mut(),
+            ctx.accounts.treasury.as_ref(),
+            &ctx.accounts.maker.to_account_info(),
+        )?;
+
+        emit!(OrderCancelledSource {
+            order_id: order.order_id,
+            canceller: ctx.accounts.canceller.key(),
+            refunded_amount: order.remaining_amount,
+            keeper_reward,
+            deposit_refunded: deposit_refund,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order.to_account_info()])?,
+        });
+
+        // Closing accounts handled by Anchor via `close = rent_payer`
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_order_destination_native(
+        ctx: Context<FillOrderDestinationNativeAccounts>,
+        order_id: [u8; 32],
+        maker_on_source: Pubkey,
+        maker_evm_address: Option<[u8; 20]>,
+        amount_on_destination: u64, // Lamports
+        hash_secret: [u8; 32],
+        hash_algorithm: HashAlgorithm,
+        timelocks: Timelocks,
+        source: SourceCommitment,
+        source_cancellation_timestamp: i64,
+        safety_deposit_sol: u64,
+        public_cancel_reward_bps: u16,
+        auction: AuctionDetails,
+        destination: DestinationDescriptor,
+        metadata: Option<DonationMetadata>,
+        integrator_fee: Option<IntegratorFee>,
+    ) -> Result<()> {
+        let terms = DestinationOrderTerms {
+            order_id,
+            order_id_bound: order_id_bound_for(ctx.accounts.source_order.as_ref()),
+            maker_on_source,
+            maker_evm_address,
+            token_mint_destination: Pubkey::default(),
+            amount_on_destination,
+            hash_secret,
+            hash_algorithm,
+            timelocks,
+            source,
+            source_cancellation_timestamp,
+            safety_deposit_sol,
+            public_cancel_reward_bps,
+            auction,
+            destination,
+            metadata,
+            integrator_fee,
+        };
+        let current_timestamp = Clock::get()?.unix_timestamp;
+        terms.validate(current_timestamp)?;
+        terms.check_descriptor(NATIVE_SOL_DECIMALS)?;
+        terms.check_source_order(ctx.accounts.source_order.as_ref())?;
+        terms.check_order_id()?;
+        ctx.accounts.config.limits.check_destination(&terms, current_timestamp)?;
+
+        let order = &mut ctx.accounts.order_state_destination;
+        order.open(ctx.accounts.resolver.key(), terms, ctx.accounts.config.protocol_fee_bps, current_timestamp, true, 0);
+        let reservation = order.nullifier(order.key(), OrderOutcome::Reserved, None, 0);
+        reserve_hashlock(&mut ctx.accounts.hashlock_nullifier, reservation, ctx.bumps.hashlock_nullifier);
+
+        // Transfer the escrowed SOL and the safety deposit from resolver to the order_state_destination PDA
+        let ix = system_instruction::transfer(
+            &ctx.accounts.resolver.key(),
+            &order.key(),
+            amount_on_destination.checked_add(safety_deposit_sol).ok_or(EscrowError::Overflow)?,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.resolver.to_account_info(),
+                order.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        emit!(order.filled_event());
+        Ok(())
+    }
+
+    pub fn withdraw_destination_native(
+        ctx: Context<WithdrawDestinationNativeAccounts>,
+        _order_id: [u8; 32],
+        secret: Option<[u8; 32]>, // None to use the secret already in the registry
+    ) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_destination;
+        require!(order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let secret = ctx.accounts.secret_record.resolve(secret)?;
+        let order_key = order.key();
+        order.apply_withdrawal(
+            order_key,
+            ctx.accounts.caller.key(),
+            ctx.accounts.instructions_sysvar.as_ref().map(|sysvar| sysvar.as_ref()),
+            &secret,
+            now,
+        )?;
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Withdrawn, Some(secret), now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
+        let hash_algorithm = order.hash_algorithm;
+        ctx.accounts.secret_record.register(hash_algorithm, secret, now, ctx.bumps.secret_record);
+
+        // Escrowed SOL goes to the maker net of fees, the safety deposit to the caller
+        let split = split_settlement(order.amount_on_destination, order.protocol_fee_bps, order.integrator_fee)?;
+        let order_info = order.to_account_info();
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.maker.to_account_info(), split.payout)?;
+        pay_fee_lamports(&order_info, ctx.accounts.treasury.as_ref(), split.protocol_fee)?;
+        pay_fee_lamports(&order_info, ctx.accounts.integrator.as_ref(), split.integrator_fee)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.caller.to_account_info(), order.safety_deposit_sol)?;
+
+        emit!(MakerWithdrawnDestination {
+            order_id: order.order_id,
+            maker: order.maker_on_source,
+            maker_evm_address: order.maker_evm_address,
+            caller: ctx.accounts.caller.key(),
+            secret,
+            received_amount: split.payout,
+            protocol_fee: split.protocol_fee,
+            integrator_fee: split.integrator_fee,
+            deposit_released: order.safety_deposit_sol,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order_info])?,
+            relayer_reward: 0,
+            metadata: order.metadata.clone(),
+        });
+        Ok(())
+    }
+
+    pub fn relay_withdraw_destination_native(
+        ctx: Context<RelayWithdrawDestinationNativeAccounts>,
+        _order_id: [u8; 32],
+        secret: Option<[u8; 32]>, // None to use the secret already in the registry
+    ) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_destination;
+        require!(order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let secret = ctx.accounts.secret_record.resolve(secret)?;
+        order.apply_relayed_withdrawal(&secret, now)?;
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Withdrawn, Some(secret), now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
+        let hash_algorithm = order.hash_algorithm;
+        ctx.accounts.secret_record.register(hash_algorithm, secret, now, ctx.bumps.secret_record);
+
+        // Escrowed SOL goes to the maker net of fees, the safety deposit to the relayer and the maker
+        let split = split_settlement(order.amount_on_destination, order.protocol_fee_bps, order.integrator_fee)?;
+        let order_info = order.to_account_info();
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.maker.to_account_info(), split.payout)?;
+        pay_fee_lamports(&order_info, ctx.accounts.treasury.as_ref(), split.protocol_fee)?;
+        pay_fee_lamports(&order_info, ctx.accounts.integrator.as_ref(), split.integrator_fee)?;
+        let relayer_reward = bps_share(order.safety_deposit_sol, ctx.accounts.config.relayer_reward_bps)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.relayer.to_account_info(), relayer_reward)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.maker.to_account_info(), order.safety_deposit_sol - relayer_reward)?;
+
+        emit!(MakerWithdrawnDestination {
+            order_id: order.order_id,
+            maker: order.maker_on_source,
+            maker_evm_address: order.maker_evm_address,
+            caller: ctx.accounts.relayer.key(),
+            secret,
+            received_amount: split.payout,
+            protocol_fee: split.protocol_fee,
+            integrator_fee: split.integrator_fee,
+            deposit_released: order.safety_deposit_sol,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order_info])?,
+            relayer_reward,
+            metadata: order.metadata.clone(),
+        });
+        Ok(())
+    }
+
+    pub fn cancel_destination_native(ctx: Context<CancelDestinationNativeAccounts>, _order_id: [u8; 32]) -> Result<()> {
+        let order = &mut ctx.accounts.order_state_destination;
+        require!(order.is_native, EscrowError::OrderKindMismatch);
+        let now = Clock::get()?.unix_timestamp;
+        let keeper_reward = order.apply_cancellation(ctx.accounts.canceller.key(), now)?;
+
+        write_nullifiers(
+            order.nullifier(order.key(), OrderOutcome::Cancelled, None, now),
+            (&mut ctx.accounts.order_nullifier, ctx.bumps.order_nullifier),
+            &mut ctx.accounts.hashlock_nullifier,
+        );
+
+        // Refund the escrowed SOL and the deposit to the resolver, minus the keeper reward
+        let order_info = order.to_account_info();
+        let resolver_refund = order
+            .amount_on_destination
+            .checked_add(order.safety_deposit_sol - keeper_reward)
+            .ok_or(EscrowError::Overflow)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.canceller.to_account_info(), keeper_reward)?;
+        transfer_lamports_from_pda(&order_info, &ctx.accounts.resolver.to_account_info(), resolver_refund)?;
+
+        emit!(OrderCancelledByResolverDestination {
+            order_id: order.order_id,
+            resolver: order.resolver,
+            canceller: ctx.accounts.canceller.key(),
+            keeper_reward,
+            deposit_refunded: order.safety_deposit_sol,
+            rent_payer: order.rent_payer,
+            rent_refunded: rent_to_refund(&[order_info])?,
+        });
+        Ok(())
+    }
+}
+
+// --- Account Structs ---
+
+#[derive(Accounts)]
+pub struct InitializeConfigAccounts<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = ProtocolConfig::LEN,
+        seeds = [CONFIG_SEED],
+        bump
     )]
-    pub escrow_authority: UncheckedAccount<'info>,
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // Must be the program's upgrade authority
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::FusionEscrowSolana>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ EscrowError::NotUpgradeAuthority)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfigAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ EscrowError::NotAdmin
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(resolver: Pubkey)]
+pub struct RegisterResolverAccounts<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ EscrowError::NotAdmin
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ResolverRecord::LEN,
+        seeds = [RESOLVER_SEED_PREFIX, resolver.as_ref()],
+        bump
+    )]
+    pub resolver_record: Account<'info, ResolverRecord>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(resolver: Pubkey)]
+pub struct SetResolverStatusAccounts<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ EscrowError::NotAdmin
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [RESOLVER_SEED_PREFIX, resolver.as_ref()],
+        bump = resolver_record.bump
+    )]
+    pub resolver_record: Account<'info, ResolverRecord>,
+}
+
+#[derive(Accounts)]
+pub struct DepositStakeAccounts<'info> {
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = ResolverStake::LEN,
+        seeds = [STAKE_SEED_PREFIX, resolver.key().as_ref()],
+        bump
+    )]
+    pub resolver_stake: Account<'info, ResolverStake>,
+
+    #[account(mut)]
+    pub resolver: Signer<'info>,
 
-    pub token_mint_source: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-#[instruction(order_id: [u8; 32])]
-pub struct WithdrawSourceAccounts<'info> {
-    #[account(
-        mut,
-        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_id.as_ref()],
-        bump, // Anchor will verify bump matches order_state_source.escrow_authority_bump if it was stored
-        has_one = resolver, // Ensures ctx.accounts.resolver.key == order_state_source.resolver
-        close = resolver // Close order_state_source account and send lamports to resolver
-    )]
-    pub order_state_source: Account<'info, OrderStateSource>,
+#[derive(Accounts)]
+pub struct RequestUnstakeAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [STAKE_SEED_PREFIX, resolver.key().as_ref()],
+        bump = resolver_stake.bump,
+        has_one = resolver
+    )]
+    pub resolver_stake: Account<'info, ResolverStake>,
+
+    pub resolver: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawStakeAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [STAKE_SEED_PREFIX, resolver.key().as_ref()],
+        bump = resolver_stake.bump,
+        has_one = resolver
+    )]
+    pub resolver_stake: Account<'info, ResolverStake>,
+
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct AcceptOrderSourceAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_id.as_ref()],
+        bump,
+        has_one = resolver
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    pub resolver: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [STAKE_SEED_PREFIX, resolver.key().as_ref()],
+        bump = resolver_stake.bump,
+        constraint = resolver_stake.staked_amount >= config.staking.min_stake @ EscrowError::InsufficientStake
+    )]
+    pub resolver_stake: Account<'info, ResolverStake>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct ReportRevealedSecretAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_id.as_ref()],
+        bump
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    // Seeded by the hash of one secret of the tree, which the handler proves against the order's root
+    #[account(seeds = [SECRET_SEED_PREFIX, secret_record.hashlock.as_ref()], bump = secret_record.bump)]
+    pub secret_record: Account<'info, SecretRecord>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdminAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = config.pending_admin == Some(new_admin.key()) @ EscrowError::NotPendingAdmin
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    pub new_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32], resolver_address: Pubkey, amount: u64, hash_secret: [u8; 32])]
+pub struct CreateOrderSourceAccounts<'info> {
+    #[account(
+        init,
+        payer = maker,
+        space = OrderStateSource::LEN,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, maker.key().as_ref(), order_id.as_ref()],
+        bump
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    /// CHECK: Must not exist yet; it is created when an order at this address closes.
+    #[account(
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump,
+        constraint = order_nullifier.data_is_empty() @ EscrowError::AlreadyNullified
+    )]
+    pub order_nullifier: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, maker.key().as_ref(), hash_secret.as_ref()],
+        bump
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>, // Reserves the hashlock while the order is open
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [RESOLVER_SEED_PREFIX, resolver_address.as_ref()],
+        bump = resolver_record.bump,
+        constraint = resolver_record.status == ResolverStatus::Active @ EscrowError::ResolverNotActive
+    )]
+    pub resolver_record: Account<'info, ResolverRecord>, // Registered, active resolver
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::authority = maker,
+        token::token_program = token_program
+    )]
+    pub maker_token_account_source: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = maker,
+        token::mint = token_mint_source,
+        token::authority = escrow_authority, // PDA is authority
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref(), b"token_source"], // Unique seed for this token account
+        bump
+    )]
+    pub escrow_token_account_source: InterfaceAccount<'info, TokenAccount>,
+    
+    /// CHECK: This PDA acts as the authority for the escrow_token_account_source.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_source: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(intent: OrderIntent)]
+pub struct CreateOrderSourceWithIntentAccounts<'info> {
+    #[account(
+        init,
+        payer = resolver,
+        space = OrderStateSource::LEN,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, intent.maker.as_ref(), intent.terms.order_id.as_ref()],
+        bump
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    /// CHECK: Must not exist yet; it is created when an order at this address closes.
+    #[account(
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump,
+        constraint = order_nullifier.data_is_empty() @ EscrowError::AlreadyNullified
+    )]
+    pub order_nullifier: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = resolver,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, intent.maker.as_ref(), intent.terms.hash_secret.as_ref()],
+        bump
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>, // Reserves the hashlock while the order is open
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [RESOLVER_SEED_PREFIX, intent.terms.resolver.as_ref()],
+        bump = resolver_record.bump,
+        constraint = resolver_record.status == ResolverStatus::Active @ EscrowError::ResolverNotActive
+    )]
+    pub resolver_record: Account<'info, ResolverRecord>, // Registered, active resolver
+
+    #[account(
+        init,
+        payer = resolver,
+        space = IntentNonce::LEN,
+        seeds = [INTENT_NONCE_SEED_PREFIX, intent.maker.as_ref(), intent.nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub intent_nonce: Account<'info, IntentNonce>, // Existing nonce account makes a replayed intent fail
+
+    #[account(mut, address = intent.terms.resolver @ EscrowError::CallerNotResolver)]
+    pub resolver: Signer<'info>, // Submits the intent, pays rent and fronts the safety deposit
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::authority = intent.maker,
+        token::token_program = token_program
+    )]
+    pub maker_token_account_source: InterfaceAccount<'info, TokenAccount>, // Must delegate the order amount to escrow_authority
+
+    #[account(
+        init,
+        payer = resolver,
+        token::mint = token_mint_source,
+        token::authority = escrow_authority,
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref(), b"token_source"],
+        bump
+    )]
+    pub escrow_token_account_source: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: This PDA is the escrow token authority and the maker's delegate for the order amount.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(address = intent.terms.token_mint_source, mint::token_program = token_program)]
+    pub token_mint_source: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Address-checked instructions sysvar, read to find the maker's Ed25519 signature.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32], secret: [u8; 32])]
+pub struct WithdrawSourceAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_id.as_ref()],
+        bump, // Anchor will verify bump matches order_state_source.escrow_authority_bump if it was stored
+        has_one = resolver, // Ensures ctx.accounts.resolver.key == order_state_source.resolver
+        // Closed to the rent payer by the handler once the last part is filled
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    #[account(mut, address = order_state_source.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = caller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Option<Account<'info, Nullifier>>, // Required with the fill that completes the order, rejected before
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_state_source.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_source.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Option<Account<'info, Nullifier>>,
+
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, order_state_source.hash_algorithm.hash(&secret).as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>, // Keyed by the hash of this fill's secret
+
+    #[account(mut)]
+    pub caller: Signer<'info>, // The resolver, or anyone holding the secret during public withdrawal
+
+    #[account(mut)]
+    pub resolver: SystemAccount<'info>, // Owns the payout token account
+
+    #[account(
+        init_if_needed,
+        payer = caller,
+        associated_token::mint = token_mint_source,
+        associated_token::authority = resolver, // Tokens always go to the designated resolver, whoever calls
+        associated_token::token_program = token_program
+    )]
+    pub resolver_token_account_source: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: settlements stay open
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::authority = config.treasury,
+        token::token_program = token_program
+    )]
+    pub protocol_fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // Required when a protocol fee is due
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::token_program = token_program,
+        constraint = order_state_source.integrator_fee.map(|fee| fee.recipient) == Some(integrator_fee_token_account.owner)
+            @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator_fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // Required when an integrator fee is due
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::authority = escrow_authority,
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref(), b"token_source"],
+        bump, // We might need to store this bump on OrderStateSource if not derivable or pass it
+        // Closed to the rent payer by the handler once the last part is filled
+    )]
+    pub escrow_token_account_source: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for the escrow token account.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump = order_state_source.escrow_authority_bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+    
+    #[account(
+        mut, // Withheld transfer fees are harvested into the mint before the escrow token account is closed
+        address = order_state_source.token_mint_source,
+        mint::token_program = token_program
+    )]
+    pub token_mint_source: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct CancelSourceAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_id.as_ref()],
+        bump,
+        has_one = maker, // Ensures funds go back to original maker
+        close = rent_payer
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    #[account(mut, address = order_state_source.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent and safety deposit, so it gets both back
+
+    #[account(
+        init,
+        payer = canceller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_state_source.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_source.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>, // Receiver of SOL, not necessarily signer if anyone can cancel
+
+    #[account(
+        init_if_needed,
+        payer = canceller,
+        associated_token::mint = token_mint_source,
+        associated_token::authority = maker, // Refunds always go to the maker, whoever cancels
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_source: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub canceller: Signer<'info>, // Maker or resolver during exclusive cancellation, anyone afterwards
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: refunds stay open
+
+    #[account(
+        mut,
+        seeds = [STAKE_SEED_PREFIX, order_state_source.resolver.as_ref()],
+        bump = resolver_stake.bump
+    )]
+    pub resolver_stake: Option<Account<'info, ResolverStake>>, // Required to slash the resolver of an accepted order
+
+    #[account(mut, address = config.treasury)]
+    pub treasury: Option<SystemAccount<'info>>, // Receives the part of a slash not paid to the maker
+
+    /// CHECK: Secret registry entry of the order's hashlock, evidence of a secret the resolver left unused. May not exist.
+    /// Never exists for partial-fill orders, whose reveals are booked by `report_revealed_secret`.
+    #[account(seeds = [SECRET_SEED_PREFIX, order_state_source.hash_secret.as_ref()], bump)]
+    pub secret_record: UncheckedAccount<'info>,
+
+    /// CHECK: Destination hashlock nullifier, which exists once the order was filled on Solana. May not exist.
+    #[account(seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_source.hash_secret.as_ref()], bump)]
+    pub destination_hashlock_nullifier: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::authority = escrow_authority,
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref(), b"token_source"],
+        bump,
+        // Closed to the rent payer by the handler through the token program
+    )]
+    pub escrow_token_account_source: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump = order_state_source.escrow_authority_bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut, // Withheld transfer fees are harvested into the mint before the escrow token account is closed
+        address = order_state_source.token_mint_source,
+        mint::token_program = token_program
+    )]
+    pub token_mint_source: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+
+#[derive(Accounts)]
+#[instruction(
+    order_id: [u8; 32],
+    maker_on_source: Pubkey,
+    maker_evm_address: Option<[u8; 20]>,
+    amount_on_destination: u64,
+    hash_secret: [u8; 32]
+)]
+pub struct FillOrderDestinationAccounts<'info> {
+    #[account(
+        init,
+        payer = resolver,
+        space = OrderStateDestination::LEN,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, resolver.key().as_ref(), order_id.as_ref()],
+        bump
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    /// CHECK: Must not exist yet; it is created when an order at this address closes.
+    #[account(
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump,
+        constraint = order_nullifier.data_is_empty() @ EscrowError::AlreadyNullified
+    )]
+    pub order_nullifier: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = resolver,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, hash_secret.as_ref()],
+        bump
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>, // Reserves the hashlock while the order is open
+
+    pub source_order: Option<Account<'info, OrderStateSource>>, // Same-chain source leg, checked against the terms
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [RESOLVER_SEED_PREFIX, resolver.key().as_ref()],
+        bump = resolver_record.bump,
+        constraint = resolver_record.status == ResolverStatus::Active @ EscrowError::ResolverNotActive
+    )]
+    pub resolver_record: Account<'info, ResolverRecord>, // Registered, active resolver
+
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = resolver,
+        token::token_program = token_program
+    )]
+    pub resolver_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        init,
+        payer = resolver,
+        token::mint = token_mint_destination,
+        token::authority = escrow_authority,
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref(), b"token_dest"], // Unique seed
+        bump
+    )]
+    pub escrow_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref()], // One authority per order account
+        bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(mint::token_program = token_program)]
+    pub token_mint_destination: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct WithdrawDestinationAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.resolver.as_ref(), order_id.as_ref()],
+        bump,
+        // has_one = maker_on_source, // This check is tricky if maker_on_source is not a Solana Pubkey directly usable as signer
+        constraint = order_state_destination.maker_on_source == maker.key() @ EscrowError::CallerNotMaker,
+        close = rent_payer
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    #[account(mut, address = order_state_destination.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = caller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_destination.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, order_state_destination.hash_secret.as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>,
+
+    #[account(mut)]
+    pub caller: Signer<'info>, // The maker (or submitter of an EVM maker's signature), or anyone during public withdrawal
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>, // maker_on_source, the payout recipient for EVM makers
+
+    #[account(
+        init_if_needed,
+        payer = caller,
+        associated_token::mint = token_mint_destination,
+        associated_token::authority = maker, // Tokens always go to the maker, whoever calls
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: settlements stay open
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = config.treasury,
+        token::token_program = token_program
+    )]
+    pub protocol_fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // Required when a protocol fee is due
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::token_program = token_program,
+        constraint = order_state_destination.integrator_fee.map(|fee| fee.recipient) == Some(integrator_fee_token_account.owner)
+            @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator_fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // Required when an integrator fee is due
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = escrow_authority,
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref(), b"token_dest"],
+        bump,
+        // Closed to the rent payer by the handler through the token program
+    )]
+    pub escrow_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump = order_state_destination.escrow_authority_bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Address-checked instructions sysvar, only needed to find an EVM maker's secp256k1 signature.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut, // Withheld transfer fees are harvested into the mint before the escrow token account is closed
+        address = order_state_destination.token_mint_destination,
+        mint::token_program = token_program
+    )]
+    pub token_mint_destination: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct RelayWithdrawDestinationAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.resolver.as_ref(), order_id.as_ref()],
+        bump,
+        // has_one = maker_on_source, // This check is tricky if maker_on_source is not a Solana Pubkey directly usable as signer
+        constraint = order_state_destination.maker_on_source == maker.key() @ EscrowError::CallerNotMaker,
+        close = rent_payer
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    #[account(mut, address = order_state_destination.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = relayer,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_destination.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, order_state_destination.hash_secret.as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>, // Anyone holding the secret
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>, // maker_on_source, the payout recipient for EVM makers
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        associated_token::mint = token_mint_destination,
+        associated_token::authority = maker, // Same payout account as a direct withdrawal
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: settlements stay open
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = config.treasury,
+        token::token_program = token_program
+    )]
+    pub protocol_fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // Required when a protocol fee is due
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::token_program = token_program,
+        constraint = order_state_destination.integrator_fee.map(|fee| fee.recipient) == Some(integrator_fee_token_account.owner)
+            @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator_fee_token_account: Option<InterfaceAccount<'info, TokenAccount>>, // Required when an integrator fee is due
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = escrow_authority,
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref(), b"token_dest"],
+        bump,
+        // Closed to the rent payer by the handler through the token program
+    )]
+    pub escrow_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump = order_state_destination.escrow_authority_bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut, // Withheld transfer fees are harvested into the mint before the escrow token account is closed
+        address = order_state_destination.token_mint_destination,
+        mint::token_program = token_program
+    )]
+    pub token_mint_destination: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct CancelDestinationAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.resolver.as_ref(), order_id.as_ref()],
+        bump,
+        has_one = resolver,
+        close = rent_payer
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    #[account(mut, address = order_state_destination.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = canceller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_destination.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(mut)]
+    pub resolver: SystemAccount<'info>, // Gets funds back
+
+    #[account(
+        init_if_needed,
+        payer = canceller,
+        associated_token::mint = token_mint_destination,
+        associated_token::authority = resolver, // Refunds always go to the resolver, whoever cancels
+        associated_token::token_program = token_program
+    )]
+    pub resolver_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub canceller: Signer<'info>, // Resolver during exclusive cancellation, anyone afterwards
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = escrow_authority,
+        token::token_program = token_program,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref(), b"token_dest"],
+        bump,
+        // Closed to the rent payer by the handler through the token program
+    )]
+    pub escrow_token_account_destination: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump = order_state_destination.escrow_authority_bump
+    )]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut, // Withheld transfer fees are harvested into the mint before the escrow token account is closed
+        address = order_state_destination.token_mint_destination,
+        mint::token_program = token_program
+    )]
+    pub token_mint_destination: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(hashlock: [u8; 32])]
+pub struct ReadSecretAccounts<'info> {
+    #[account(seeds = [SECRET_SEED_PREFIX, hashlock.as_ref()], bump = secret_record.bump)]
+    pub secret_record: Account<'info, SecretRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(hash_algorithm: HashAlgorithm, secret: [u8; 32])]
+pub struct PublishSecretAccounts<'info> {
+    #[account(
+        init_if_needed,
+        payer = publisher,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, hash_algorithm.hash(&secret).as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>,
+
+    #[account(mut)]
+    pub publisher: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct AtomicSettleAccounts<'info> {
+    // Both legs and their token accounts are boxed to keep this struct's validation within the BPF stack frame
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_id.as_ref()],
+        bump,
+        has_one = resolver,
+        close = source_rent_payer
+    )]
+    pub order_state_source: Box<Account<'info, OrderStateSource>>,
+
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, resolver.key().as_ref(), order_id.as_ref()],
+        bump,
+        has_one = resolver,
+        constraint = order_state_destination.maker_on_source == maker.key() @ EscrowError::CallerNotMaker,
+        close = destination_rent_payer
+    )]
+    pub order_state_destination: Box<Account<'info, OrderStateDestination>>,
+
+    #[account(mut, address = order_state_source.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub source_rent_payer: SystemAccount<'info>, // Gets the source rent and safety deposit back
+
+    #[account(mut, address = order_state_destination.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub destination_rent_payer: SystemAccount<'info>, // Gets the destination rent and safety deposit back
+
+    #[account(
+        init,
+        payer = resolver,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump
+    )]
+    pub source_order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_state_source.hash_secret.as_ref()],
+        bump = source_hashlock_nullifier.bump,
+        constraint = source_hashlock_nullifier.order == order_state_source.key() @ EscrowError::NullifierMismatch
+    )]
+    pub source_hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        init,
+        payer = resolver,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump
+    )]
+    pub destination_order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.hash_secret.as_ref()],
+        bump = destination_hashlock_nullifier.bump,
+        constraint = destination_hashlock_nullifier.order == order_state_destination.key() @ EscrowError::NullifierMismatch
+    )]
+    pub destination_hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, order_state_source.hash_secret.as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>, // Shared by both legs, which use the same hashlock
+
+    #[account(mut)]
+    pub resolver: Signer<'info>, // Resolver of both legs
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>, // maker_on_source, the destination payout recipient
+
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        associated_token::mint = token_mint_source,
+        associated_token::authority = resolver,
+        associated_token::token_program = token_program_source
+    )]
+    pub resolver_token_account_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = resolver,
+        associated_token::mint = token_mint_destination,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program_destination
+    )]
+    pub maker_token_account_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: settlements stay open
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::authority = config.treasury,
+        token::token_program = token_program_source
+    )]
+    pub protocol_fee_token_account_source: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::token_program = token_program_source,
+        constraint = order_state_source.integrator_fee.map(|fee| fee.recipient) == Some(integrator_fee_token_account_source.owner)
+            @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator_fee_token_account_source: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = config.treasury,
+        token::token_program = token_program_destination
+    )]
+    pub protocol_fee_token_account_destination: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::token_program = token_program_destination,
+        constraint = order_state_destination.integrator_fee.map(|fee| fee.recipient) == Some(integrator_fee_token_account_destination.owner)
+            @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator_fee_token_account_destination: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_source,
+        token::authority = escrow_authority_source,
+        token::token_program = token_program_source,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref(), b"token_source"],
+        bump
+    )]
+    pub escrow_token_account_source: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: PDA authority for the source escrow token account.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump = order_state_source.escrow_authority_bump
+    )]
+    pub escrow_authority_source: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint_destination,
+        token::authority = escrow_authority_destination,
+        token::token_program = token_program_destination,
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref(), b"token_dest"],
+        bump
+    )]
+    pub escrow_token_account_destination: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: PDA authority for the destination escrow token account.
+    #[account(
+        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump = order_state_destination.escrow_authority_bump
+    )]
+    pub escrow_authority_destination: UncheckedAccount<'info>,
+
+    #[account(
+        mut, // Withheld transfer fees are harvested into the mints before the escrow token accounts are closed
+        address = order_state_source.token_mint_source,
+        mint::token_program = token_program_source
+    )]
+    pub token_mint_source: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        address = order_state_destination.token_mint_destination,
+        mint::token_program = token_program_destination
+    )]
+    pub token_mint_destination: Box<InterfaceAccount<'info, Mint>>,
+    pub token_program_source: Interface<'info, TokenInterface>, // The legs may use Token and Token-2022 respectively
+    pub token_program_destination: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32], resolver_address: Pubkey, amount: u64, hash_secret: [u8; 32])]
+pub struct CreateOrderSourceNativeAccounts<'info> {
+    #[account(
+        init,
+        payer = maker,
+        space = OrderStateSource::LEN,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, maker.key().as_ref(), order_id.as_ref()],
+        bump
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    /// CHECK: Must not exist yet; it is created when an order at this address closes.
+    #[account(
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump,
+        constraint = order_nullifier.data_is_empty() @ EscrowError::AlreadyNullified
+    )]
+    pub order_nullifier: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, maker.key().as_ref(), hash_secret.as_ref()],
+        bump
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>, // Reserves the hashlock while the order is open
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [RESOLVER_SEED_PREFIX, resolver_address.as_ref()],
+        bump = resolver_record.bump,
+        constraint = resolver_record.status == ResolverStatus::Active @ EscrowError::ResolverNotActive
+    )]
+    pub resolver_record: Account<'info, ResolverRecord>, // Registered, active resolver
+
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32], secret: [u8; 32])]
+pub struct WithdrawSourceNativeAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_id.as_ref()],
+        bump,
+        has_one = resolver,
+        // Closed to the rent payer by the handler once the last part is filled
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    #[account(mut, address = order_state_source.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = caller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Option<Account<'info, Nullifier>>, // Required with the fill that completes the order, rejected before
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_state_source.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_source.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Option<Account<'info, Nullifier>>,
+
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, order_state_source.hash_algorithm.hash(&secret).as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>, // Keyed by the hash of this fill's secret
+
+    #[account(mut)]
+    pub caller: Signer<'info>, // The resolver, or anyone holding the secret during public withdrawal
+
+    #[account(mut)]
+    pub resolver: SystemAccount<'info>, // Receives the escrowed SOL
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: settlements stay open
+
+    #[account(mut, address = config.treasury)]
+    pub treasury: Option<SystemAccount<'info>>, // Required when a protocol fee is due
+
+    #[account(
+        mut,
+        constraint = order_state_source.integrator_fee.map(|fee| fee.recipient) == Some(integrator.key()) @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator: Option<SystemAccount<'info>>, // Required when an integrator fee is due
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct CancelSourceNativeAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_id.as_ref()],
+        bump,
+        has_one = maker,
+        close = rent_payer
+    )]
+    pub order_state_source: Account<'info, OrderStateSource>,
+
+    #[account(mut, address = order_state_source.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent and safety deposit, so it gets both back
+
+    #[account(
+        init,
+        payer = canceller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_source.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_SOURCE, order_state_source.maker.as_ref(), order_state_source.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_source.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub canceller: Signer<'info>, // Maker or resolver during exclusive cancellation, anyone afterwards
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: refunds stay open
+
+    #[account(
+        mut,
+        seeds = [STAKE_SEED_PREFIX, order_state_source.resolver.as_ref()],
+        bump = resolver_stake.bump
+    )]
+    pub resolver_stake: Option<Account<'info, ResolverStake>>, // Required to slash the resolver of an accepted order
+
+    #[account(mut, address = config.treasury)]
+    pub treasury: Option<SystemAccount<'info>>, // Receives the part of a slash not paid to the maker
+
+    /// CHECK: Secret registry entry of the order's hashlock, evidence of a secret the resolver left unused. May not exist.
+    /// Never exists for partial-fill orders, whose reveals are booked by `report_revealed_secret`.
+    #[account(seeds = [SECRET_SEED_PREFIX, order_state_source.hash_secret.as_ref()], bump)]
+    pub secret_record: UncheckedAccount<'info>,
+
+    /// CHECK: Destination hashlock nullifier, which exists once the order was filled on Solana. May not exist.
+    #[account(seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_source.hash_secret.as_ref()], bump)]
+    pub destination_hashlock_nullifier: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    order_id: [u8; 32],
+    maker_on_source: Pubkey,
+    maker_evm_address: Option<[u8; 20]>,
+    amount_on_destination: u64,
+    hash_secret: [u8; 32]
+)]
+pub struct FillOrderDestinationNativeAccounts<'info> {
+    #[account(
+        init,
+        payer = resolver,
+        space = OrderStateDestination::LEN,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, resolver.key().as_ref(), order_id.as_ref()],
+        bump
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    /// CHECK: Must not exist yet; it is created when an order at this address closes.
+    #[account(
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump,
+        constraint = order_nullifier.data_is_empty() @ EscrowError::AlreadyNullified
+    )]
+    pub order_nullifier: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = resolver,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, hash_secret.as_ref()],
+        bump
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>, // Reserves the hashlock while the order is open
+
+    pub source_order: Option<Account<'info, OrderStateSource>>, // Same-chain source leg, checked against the terms
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ EscrowError::ProtocolPaused
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [RESOLVER_SEED_PREFIX, resolver.key().as_ref()],
+        bump = resolver_record.bump,
+        constraint = resolver_record.status == ResolverStatus::Active @ EscrowError::ResolverNotActive
+    )]
+    pub resolver_record: Account<'info, ResolverRecord>, // Registered, active resolver
+
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct WithdrawDestinationNativeAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.resolver.as_ref(), order_id.as_ref()],
+        bump,
+        constraint = order_state_destination.maker_on_source == maker.key() @ EscrowError::CallerNotMaker,
+        close = rent_payer
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    #[account(mut, address = order_state_destination.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = caller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_destination.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        init_if_needed,
+        payer = caller,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, order_state_destination.hash_secret.as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>,
+
+    #[account(mut)]
+    pub caller: Signer<'info>, // The maker (or submitter of an EVM maker's signature), or anyone during public withdrawal
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>, // Receives the escrowed SOL
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: settlements stay open
+
+    #[account(mut, address = config.treasury)]
+    pub treasury: Option<SystemAccount<'info>>, // Required when a protocol fee is due
+
+    #[account(
+        mut,
+        constraint = order_state_destination.integrator_fee.map(|fee| fee.recipient) == Some(integrator.key()) @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator: Option<SystemAccount<'info>>, // Required when an integrator fee is due
+
+    /// CHECK: Address-checked instructions sysvar, only needed to find an EVM maker's secp256k1 signature.
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct RelayWithdrawDestinationNativeAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.resolver.as_ref(), order_id.as_ref()],
+        bump,
+        constraint = order_state_destination.maker_on_source == maker.key() @ EscrowError::CallerNotMaker,
+        close = rent_payer
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    #[account(mut, address = order_state_destination.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = relayer,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_destination.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = SecretRecord::LEN,
+        seeds = [SECRET_SEED_PREFIX, order_state_destination.hash_secret.as_ref()],
+        bump
+    )]
+    pub secret_record: Account<'info, SecretRecord>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>, // Anyone holding the secret
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>, // Receives the escrowed SOL
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>, // Not pause-gated: settlements stay open
+
+    #[account(mut, address = config.treasury)]
+    pub treasury: Option<SystemAccount<'info>>, // Required when a protocol fee is due
+
+    #[account(
+        mut,
+        constraint = order_state_destination.integrator_fee.map(|fee| fee.recipient) == Some(integrator.key()) @ EscrowError::InvalidFeeAccount
+    )]
+    pub integrator: Option<SystemAccount<'info>>, // Required when an integrator fee is due
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_id: [u8; 32])]
+pub struct CancelDestinationNativeAccounts<'info> {
+    #[account(
+        mut,
+        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.resolver.as_ref(), order_id.as_ref()],
+        bump,
+        has_one = resolver,
+        close = rent_payer
+    )]
+    pub order_state_destination: Account<'info, OrderStateDestination>,
+
+    #[account(mut, address = order_state_destination.rent_payer @ EscrowError::InvalidRentPayer)]
+    pub rent_payer: SystemAccount<'info>, // Paid the order's rent, so it gets it back
+
+    #[account(
+        init,
+        payer = canceller,
+        space = Nullifier::LEN,
+        seeds = [NULLIFIER_SEED_PREFIX, order_state_destination.key().as_ref()],
+        bump
+    )]
+    pub order_nullifier: Account<'info, Nullifier>,
+
+    #[account(
+        mut,
+        seeds = [NULLIFIER_SEED_PREFIX, ORDER_PDA_SEED_PREFIX_DESTINATION, order_state_destination.hash_secret.as_ref()],
+        bump = hashlock_nullifier.bump,
+        constraint = hashlock_nullifier.order == order_state_destination.key() @ EscrowError::NullifierMismatch
+    )]
+    pub hashlock_nullifier: Account<'info, Nullifier>,
+
+    #[account(mut)]
+    pub resolver: SystemAccount<'info>, // Gets the escrowed SOL back
+
+    #[account(mut)]
+    pub canceller: Signer<'info>, // Resolver during exclusive cancellation, anyone afterwards
+
+    pub system_program: Program<'info, System>,
+}
+
+
+// --- State Structs ---
+
+/// Singleton protocol settings, seeded by `CONFIG_SEED`.
+#[account]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>, // Proposed by `transfer_admin`, set by `accept_admin`
+    pub treasury: Pubkey, // Owner of the protocol fee vaults and receiver of slashed stake
+    pub protocol_fee_bps: u16, // Snapshotted into each order at creation
+    pub relayer_reward_bps: u16, // Share of a destination safety deposit paid to a relayer
+    pub paused: bool, // Blocks new orders only
+    pub limits: ProtocolLimits,
+    pub staking: StakingParams,
+    pub bump: u8,
+}
+
+impl ProtocolConfig {
+    // 8 (discriminator) + 32 (admin) + 33 (pending_admin) + 32 (treasury) + 2 (protocol_fee_bps)
+    // + 2 (relayer_reward_bps) + 1 (paused) + ProtocolLimits::LEN + StakingParams::LEN + 1 (bump)
+    pub const LEN: usize = 8 + 32 + 33 + 32 + 2 + 2 + 1 + ProtocolLimits::LEN + StakingParams::LEN + 1;
+
+    pub fn validate(&self) -> Result<()> {
+        self.limits.validate()?;
+        self.staking.validate()?;
+        // Protocol and integrator fees together can never exceed the settled amount
+        require_gte!(
+            BPS_DENOMINATOR,
+            self.protocol_fee_bps.saturating_add(self.limits.max_integrator_fee_bps),
+            EscrowError::InvalidBasisPoints
+        );
+        require_gte!(BPS_DENOMINATOR, self.relayer_reward_bps, EscrowError::InvalidBasisPoints);
+        Ok(())
+    }
+
+    pub fn updated_event(&self) -> ConfigUpdated {
+        ConfigUpdated {
+            admin: self.admin,
+            treasury: self.treasury,
+            protocol_fee_bps: self.protocol_fee_bps,
+            relayer_reward_bps: self.relayer_reward_bps,
+            paused: self.paused,
+            limits: self.limits,
+            staking: self.staking,
+        }
+    }
+}
+
+/// Resolver staking parameters.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StakingParams {
+    pub min_stake: u64, // Needed to accept a source order
+    pub unstake_cooldown: u32, // Seconds between `request_unstake` and `withdraw_stake`
+    pub slash_amount: u64, // Taken per accepted source order that ends up cancelled
+    pub maker_compensation_bps: u16, // Share of each slash paid to the affected maker
+}
+
+impl StakingParams {
+    pub const LEN: usize = 8 + 4 + 8 + 2;
+
+    pub fn validate(&self) -> Result<()> {
+        require_gte!(BPS_DENOMINATOR, self.maker_compensation_bps, EscrowError::InvalidBasisPoints);
+        Ok(())
+    }
+}
+
+/// Permanent tombstone of a closed order. One copy is seeded by the order account address and one by the
+/// hashlock, so neither the order_id nor a hashlock whose secret may be public can be reused. Source hashlocks
+/// are reserved per maker, since anyone may open a source order and could otherwise block a hashlock seen in
+/// a broadcast intent or on the other chain; destination fills come from registered resolvers only, and their
+/// reservation is global so cancellations can check whether an order bound for Solana was filled.
+/// The hashlock copy is created with the order as a `Reserved` record and overwritten with the outcome on close.
+#[account]
+pub struct Nullifier {
+    pub order: Pubkey, // Order account, which also pins the creator and order_id
+    pub order_id: [u8; 32],
+    pub hash_secret: [u8; 32],
+    pub outcome: OrderOutcome,
+    pub secret: Option<[u8; 32]>, // Revealed preimage for withdrawn orders (the last one for partial fills)
+    pub closed_at: i64, // 0 while reserved
+    pub bump: u8,
+}
+
+impl Nullifier {
+    // 8 (discriminator) + 32 (order) + 32 (order_id) + 32 (hash_secret) + 1 (outcome) + 33 (secret)
+    // + 8 (closed_at) + 1 (bump)
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 1 + 33 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderOutcome {
+    Withdrawn,
+    Cancelled,
+    Reserved, // Hashlock nullifier of an order that is still open
+}
+
+/// Preimage of a hashlock, kept after the orders using it close so watchers and other programs can read it
+/// from an account (or through `registered_secret`) instead of parsing withdrawal logs.
+#[account]
+pub struct SecretRecord {
+    pub hashlock: [u8; 32],
+    pub hash_algorithm: HashAlgorithm,
+    pub secret: [u8; 32],
+    pub revealed_at: i64, // First withdrawal or publication that revealed it; 0 until registered
+    pub bump: u8,
+}
+
+impl SecretRecord {
+    // 8 (discriminator) + 32 (hashlock) + HashAlgorithm::LEN + 32 (secret) + 8 (revealed_at) + 1 (bump)
+    pub const LEN: usize = 8 + 32 + HashAlgorithm::LEN + 32 + 8 + 1;
+
+    pub fn is_registered(&self) -> bool {
+        self.revealed_at != 0
+    }
+
+    /// The secret a withdrawal should use: the one supplied, or else the registered one.
+    pub fn resolve(&self, secret: Option<[u8; 32]>) -> Result<[u8; 32]> {
+        match secret {
+            Some(secret) => Ok(secret),
+            None => {
+                require!(self.is_registered(), EscrowError::SecretNotRegistered);
+                Ok(self.secret)
+            }
+        }
+    }
+
+    /// Stores a secret that a withdrawal has just checked against the hashlock; later reveals are no-ops.
+    pub fn register(&mut self, hash_algorithm: HashAlgorithm, secret: [u8; 32], revealed_at: i64, bump: u8) {
+        if self.is_registered() {
+            return;
+        }
+        self.hashlock = hash_algorithm.hash(&secret);
+        self.hash_algorithm = hash_algorithm;
+        self.secret = secret;
+        self.revealed_at = revealed_at;
+        self.bump = bump;
+    }
+}
+
+/// Lamport stake vault of a resolver, seeded by `STAKE_SEED_PREFIX` and the resolver key.
+#[account]
+pub struct ResolverStake {
+    pub resolver: Pubkey,
+    pub staked_amount: u64,
+    pub pending_unstake_amount: u64, // Still slashable until withdrawn
+    pub unstake_available_at: i64,
+    pub locked_until: i64, // Latest public cancellation deadline among accepted orders
+    pub bump: u8,
+}
+
+impl ResolverStake {
+    // 8 (discriminator) + 32 (resolver) + 8 (staked_amount) + 8 (pending_unstake_amount)
+    // + 8 (unstake_available_at) + 8 (locked_until) + 1 (bump)
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 8 + 8 + 1;
+
+    /// Keeps all stake, pending or not, in the vault until `deadline`, so an accepted order can still be
+    /// slashed when it is cancelled. Never shortens an existing lock.
+    pub fn lock_until(&mut self, deadline: i64) {
+        self.locked_until = self.locked_until.max(deadline);
+    }
+
+    /// Takes up to `amount` from active stake first, then from stake waiting to be withdrawn.
+    /// Returns what was actually taken.
+    pub fn slash(&mut self, amount: u64) -> u64 {
+        let from_staked = amount.min(self.staked_amount);
+        self.staked_amount -= from_staked;
+        let from_pending = (amount - from_staked).min(self.pending_unstake_amount);
+        self.pending_unstake_amount -= from_pending;
+        from_staked + from_pending
+    }
+}
+
+/// Registry entry for a vetted resolver, seeded by `RESOLVER_SEED_PREFIX` and the resolver key.
+#[account]
+pub struct ResolverRecord {
+    pub resolver: Pubkey,
+    pub status: ResolverStatus,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl ResolverRecord {
+    // 8 (discriminator) + 32 (resolver) + 1 (status) + 8 (registered_at) + 1 (bump)
+    pub const LEN: usize = 8 + 32 + 1 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolverStatus {
+    Active,
+    Suspended,
+}
+
+/// Bounds every new order must respect, on top of its own consistency checks.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProtocolLimits {
+    pub min_safety_deposit_sol: u64,
+    pub max_parts_amount: u16,
+    pub max_public_cancel_reward_bps: u16,
+    pub max_integrator_fee_bps: u16,
+    pub source_timeout: TimeoutBounds,
+    pub destination_timeout: TimeoutBounds,
+    pub min_safety_margin: u32, // Seconds between the destination and the source cancellation deadlines
+}
+
+impl ProtocolLimits {
+    pub const LEN: usize = 8 + 2 + 2 + 2 + TimeoutBounds::LEN + TimeoutBounds::LEN + 4;
+
+    pub fn validate(&self) -> Result<()> {
+        require_gt!(self.max_parts_amount, 0, EscrowError::InvalidProtocolLimits);
+        require_gte!(BPS_DENOMINATOR, self.max_public_cancel_reward_bps, EscrowError::InvalidBasisPoints);
+        require_gte!(BPS_DENOMINATOR, self.max_integrator_fee_bps, EscrowError::InvalidBasisPoints);
+        self.source_timeout.validate()?;
+        self.destination_timeout.validate()
+    }
+
+    pub fn check_source(&self, terms: &SourceOrderTerms, current_timestamp: i64) -> Result<()> {
+        require_gte!(self.max_parts_amount, terms.parts_amount, EscrowError::ExceedsProtocolLimit);
+        self.source_timeout.check(&terms.timelocks, current_timestamp)?;
+        self.check_common(terms.safety_deposit_sol, terms.public_cancel_reward_bps, terms.integrator_fee)
+    }
+
+    /// Besides the common bounds, the maker must be able to withdraw here, and the resolver must then have
+    /// `min_safety_margin` seconds left to withdraw on the source chain with the revealed secret.
+    /// The source deadline is only checked against chain data for Solana sources (`check_source_order`). For
+    /// sources on other chains it is the resolver's own claim, since the source escrow's deadline depends on when
+    /// it was deployed and no signed term fixes it; the margin is then advisory, and a resolver that overstates
+    /// the deadline only exposes itself to the maker withdrawing here after cancelling on the source chain.
+    pub fn check_destination(&self, terms: &DestinationOrderTerms, current_timestamp: i64) -> Result<()> {
+        let cancellation = self.destination_timeout.check(&terms.timelocks, current_timestamp)?;
+        let latest_cancellation = terms
+            .source_cancellation_timestamp
+            .checked_sub(i64::from(self.min_safety_margin))
+            .ok_or(EscrowError::Overflow)?;
+        require_gte!(latest_cancellation, cancellation, EscrowError::InsufficientSafetyMargin);
+        self.check_common(terms.safety_deposit_sol, terms.public_cancel_reward_bps, terms.integrator_fee)
+    }
+
+    fn check_common(&self, safety_deposit_sol: u64, public_cancel_reward_bps: u16, integrator_fee: Option<IntegratorFee>) -> Result<()> {
+        require_gte!(safety_deposit_sol, self.min_safety_deposit_sol, EscrowError::SafetyDepositTooLow);
+        require_gte!(self.max_public_cancel_reward_bps, public_cancel_reward_bps, EscrowError::ExceedsProtocolLimit);
+        if let Some(integrator_fee) = integrator_fee {
+            require_gte!(self.max_integrator_fee_bps, integrator_fee.fee_bps, EscrowError::ExceedsProtocolLimit);
+        }
+        Ok(())
+    }
+}
+
+/// Range allowed for an order's timeout, i.e. the `cancellation` offset that ends its withdrawal window.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeoutBounds {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl TimeoutBounds {
+    pub const LEN: usize = 4 + 4;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(0 < self.min && self.min <= self.max, EscrowError::InvalidProtocolLimits);
+        Ok(())
+    }
+
+    /// Returns the cancellation deadline, after checking that the whole schedule fits in a timestamp.
+    pub fn check(&self, timelocks: &Timelocks, creation_timestamp: i64) -> Result<i64> {
+        require!(
+            self.min <= timelocks.cancellation && timelocks.cancellation <= self.max,
+            EscrowError::TimeoutOutOfBounds
+        );
+        Timelocks::deadline(creation_timestamp, timelocks.public_cancellation)?;
+        Timelocks::deadline(creation_timestamp, timelocks.cancellation)
+    }
+}
+
+#[account]
+pub struct OrderStateSource {
+    pub order_id: [u8; 32],
+    pub order_id_bound: bool,
+    pub maker: Pubkey,
+    pub resolver: Pubkey,
+    pub rent_payer: Pubkey, // Posted the safety deposit and gets the rent of the order and escrow token accounts back
+    pub token_mint_source: Pubkey, // Pubkey::default() for native SOL orders
+    pub amount: u64,
+    pub remaining_amount: u64, // Decreases with every partial fill
+    pub safety_deposit_sol: u64,
+    pub hash_secret: [u8; 32], // Hashlock, or Merkle root of the secret hashes when parts_amount > 1
+    pub hash_algorithm: HashAlgorithm,
+    pub parts_amount: u16,
+    pub creation_timestamp: i64,
+    pub timelocks: Timelocks,
+    pub public_cancel_reward_bps: u16,
+    pub auction: AuctionDetails,
+    pub destination: DestinationDescriptor,
+    pub protocol_fee_bps: u16, // Config rate at creation
+    pub metadata: Option<DonationMetadata>,
+    pub integrator_fee: Option<IntegratorFee>,
+    pub is_native: bool, // Amount is held as lamports in this PDA instead of an escrow token account
+    pub is_accepted: bool, // Resolver committed to fill the destination side with its stake at risk
+    pub revealed_part: Option<u16>, // Highest secret index reported public in time, partial-fill orders only
+    pub is_withdrawn: bool, // Set once the whole amount has been filled
+    pub is_cancelled: bool,
+    pub escrow_authority_bump: u8, // To sign for escrow token account, unused for native orders
+}
+
+impl OrderStateSource {
+    pub fn nullifier(&self, order: Pubkey, outcome: OrderOutcome, secret: Option<[u8; 32]>, closed_at: i64) -> Nullifier {
+        Nullifier {
+            order,
+            order_id: self.order_id,
+            hash_secret: self.hash_secret,
+            outcome,
+            secret,
+            closed_at,
+            bump: 0, // Set per nullifier account by `write_nullifiers`
+        }
+    }
+
+    // 8 (discriminator) + 32 (order_id) + 1 (order_id_bound) + 32 (maker) + 32 (resolver) + 32 (rent_payer)
+    // + 32 (token_mint) + 8 (amount) + 8 (remaining_amount) + 8 (safety_deposit) + 32 (hash_secret) + HashAlgorithm::LEN + 2 (parts_amount)
+    // + 8 (creation_ts) + Timelocks::LEN + 2 (public_cancel_reward_bps) + AuctionDetails::LEN + DestinationDescriptor::LEN
+    // + 2 (protocol_fee_bps) + 1 + DonationMetadata::LEN (metadata) + 1 + IntegratorFee::LEN (integrator_fee)
+    // + 1 (is_native) + 1 (is_accepted) + 3 (revealed_part) + 1 (is_withdrawn) + 1 (is_cancelled) + 1 (escrow_authority_bump)
+    pub const LEN: usize = 8 + 32 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 32 + HashAlgorithm::LEN + 2 + 8 + Timelocks::LEN
+        + 2 + AuctionDetails::LEN + DestinationDescriptor::LEN + 2 + 1 + DonationMetadata::LEN + 1 + IntegratorFee::LEN
+        + 1 + 1 + 3 + 1 + 1 + 1;
+
+    pub fn open(
+        &mut self,
+        maker: Pubkey,
+        terms: SourceOrderTerms,
+        protocol_fee_bps: u16,
+        creation_timestamp: i64,
+        is_native: bool,
+        escrow_authority_bump: u8,
+    ) {
+        self.order_id = terms.order_id;
+        self.order_id_bound = terms.order_id_bound;
+        self.maker = maker;
+        self.resolver = terms.resolver;
+        self.rent_payer = maker; // Except for intents, where the resolver pays
+
+        self.token_mint_source = terms.token_mint_source;
+        self.amount = terms.amount;
+        self.remaining_amount = terms.amount;
+        self.safety_deposit_sol = terms.safety_deposit_sol;
+        self.hash_secret = terms.hash_secret;
+        self.hash_algorithm = terms.hash_algorithm;
+        self.parts_amount = terms.parts_amount;
+        self.creation_timestamp = creation_timestamp;
+        self.timelocks = terms.timelocks;
+        self.public_cancel_reward_bps = terms.public_cancel_reward_bps;
+        self.auction = terms.auction;
+        self.destination = terms.destination;
+        self.protocol_fee_bps = protocol_fee_bps;
+        self.metadata = terms.metadata;
+        self.integrator_fee = terms.integrator_fee;
+        self.is_native = is_native;
+        self.is_accepted = false;
+        self.revealed_part = None;
+        self.is_withdrawn = false;
+        self.is_cancelled = false;
+        self.escrow_authority_bump = escrow_authority_bump; // Store bump for PDA signing
+    }
+
+    pub fn created_event(&self) -> OrderCreatedSource {
+        OrderCreatedSource {
+            order_id: self.order_id,
+            order_id_bound: self.order_id_bound,
+            maker: self.maker,
+            resolver: self.resolver,
+            rent_payer: self.rent_payer,
+            token_mint_source: self.token_mint_source,
+            amount: self.amount,
+            safety_deposit_sol: self.safety_deposit_sol,
+            hash_secret: self.hash_secret,
+            hash_algorithm: self.hash_algorithm,
+            parts_amount: self.parts_amount,
+            creation_timestamp: self.creation_timestamp,
+            timelocks: self.timelocks,
+            public_cancel_reward_bps: self.public_cancel_reward_bps,
+            auction: self.auction.clone(),
+            destination: self.destination.clone(),
+            protocol_fee_bps: self.protocol_fee_bps,
+            metadata: self.metadata.clone(),
+            integrator_fee: self.integrator_fee,
+            is_native: self.is_native,
+        }
+    }
+
+    /// Records the resolver's commitment to fill the destination side while the order can still be withdrawn.
+    pub fn accept(&mut self, current_timestamp: i64) -> Result<()> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        require!(!stage.is_cancellation(), EscrowError::TimeoutExpired);
+        require!(!self.is_accepted, EscrowError::AlreadyAccepted);
+        require!(!self.is_withdrawn, EscrowError::AlreadyWithdrawn);
+        require!(!self.is_cancelled, EscrowError::AlreadyCancelled);
+
+        self.is_accepted = true;
+        Ok(())
+    }
+
+    /// Checks a fill against the schedule, the secret and the partial fill rules, then books it.
+    /// Returns the share of the safety deposit released by this fill.
+    pub fn apply_withdrawal(
+        &mut self,
+        caller: Pubkey,
+        secret: &[u8; 32],
+        secret_index: u16,
+        merkle_proof: &[[u8; 32]],
+        fill_amount: u64,
+        current_timestamp: i64,
+    ) -> Result<u64> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        require!(stage != OrderStage::FinalityLock, EscrowError::FinalityLockActive);
+        require!(stage.is_withdrawal(), EscrowError::TimeoutExpired);
+        if stage == OrderStage::ExclusiveWithdrawal {
+            require_keys_eq!(caller, self.resolver, EscrowError::CallerNotResolver);
+        }
+        require!(!self.is_withdrawn, EscrowError::AlreadyWithdrawn);
+        require!(!self.is_cancelled, EscrowError::AlreadyCancelled);
+        require_gt!(fill_amount, 0, EscrowError::ZeroAmount);
+        require_gte!(self.remaining_amount, fill_amount, EscrowError::InvalidPartialFill);
+
+        let provided_hash = self.hash_algorithm.hash(secret);
+        if self.parts_amount > 1 {
+            require!(
+                is_valid_partial_fill(self.amount, self.remaining_amount, self.parts_amount, fill_amount, secret_index),
+                EscrowError::InvalidPartialFill
+            );
+            let leaf = merkle_leaf(secret_index, &provided_hash);
+            require!(verify_merkle_proof(merkle_proof, self.hash_secret, leaf), EscrowError::InvalidMerkleProof);
+        } else {
+            require!(secret_index == 0 && merkle_proof.is_empty(), EscrowError::InvalidPartialFill);
+            require_eq!(fill_amount, self.amount, EscrowError::InvalidPartialFill);
+            require!(provided_hash == self.hash_secret, EscrowError::InvalidSecret);
+        }
+
+        // Release the share of the safety deposit matching this fill; the last fill takes the remainder
+        let remaining_after = self.remaining_amount - fill_amount;
+        let deposit_release = self.safety_deposit_for(self.remaining_amount)? - self.safety_deposit_for(remaining_after)?;
+        self.remaining_amount = remaining_after;
+        self.is_withdrawn = remaining_after == 0;
+        Ok(deposit_release)
+    }
+
+    /// Books the whole order as filled by `atomic_settle`. Both legs settle in the same transaction, so neither the
+    /// finality lock nor the exclusive window applies; the order only has to be open and not yet cancellable.
+    /// Returns the released safety deposit.
+    pub fn apply_atomic_settlement(&mut self, secret: &[u8; 32], current_timestamp: i64) -> Result<u64> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        require!(!stage.is_cancellation(), EscrowError::TimeoutExpired);
+        require!(!self.is_withdrawn, EscrowError::AlreadyWithdrawn);
+        require!(!self.is_cancelled, EscrowError::AlreadyCancelled);
+        require_eq!(self.parts_amount, 1, EscrowError::InvalidPartialFill);
+        require!(self.hash_algorithm.hash(secret) == self.hash_secret, EscrowError::InvalidSecret);
+
+        self.remaining_amount = 0;
+        self.is_withdrawn = true;
+        Ok(self.safety_deposit_sol)
+    }
+
+    /// Checks a cancellation against the schedule and books it.
+    /// Returns the unreleased safety deposit and the part of it owed to a public canceller.
+    pub fn apply_cancellation(&mut self, canceller: Pubkey, current_timestamp: i64) -> Result<(u64, u64)> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        require!(stage.is_cancellation(), EscrowError::TimeoutNotExpired);
+        if stage == OrderStage::ExclusiveCancellation {
+            // Only the parties to the order may cancel before the public cancellation window opens
+            require!(canceller == self.maker || canceller == self.resolver, EscrowError::StageRestricted);
+        }
+        require!(!self.is_withdrawn, EscrowError::AlreadyWithdrawn);
+        require!(!self.is_cancelled, EscrowError::AlreadyCancelled);
+
+        self.is_cancelled = true;
+
+        let deposit_refund = self.safety_deposit_for(self.remaining_amount)?;
+        let keeper_reward = if stage == OrderStage::PublicCancellation {
+            bps_share(deposit_refund, self.public_cancel_reward_bps)?
+        } else {
+            0
+        };
+        Ok((deposit_refund, keeper_reward))
+    }
+
+    /// Books a reveal of secret `secret_index`, registered in `record`, as evidence against the resolver.
+    pub fn report_revealed_secret(&mut self, record: &SecretRecord, secret_index: u16, merkle_proof: &[[u8; 32]]) -> Result<()> {
+        require_gt!(self.parts_amount, 1, EscrowError::InvalidPartialFill);
+        require!(!self.is_withdrawn, EscrowError::AlreadyWithdrawn);
+        require!(!self.is_cancelled, EscrowError::AlreadyCancelled);
+        require!(record.is_registered(), EscrowError::SecretNotRegistered);
+        require!(record.hash_algorithm == self.hash_algorithm, EscrowError::InvalidSecret);
+        let leaf = merkle_leaf(secret_index, &record.hashlock);
+        require!(verify_merkle_proof(merkle_proof, self.hash_secret, leaf), EscrowError::InvalidMerkleProof);
+        require_gt!(
+            Timelocks::deadline(self.creation_timestamp, self.timelocks.public_withdrawal)?,
+            record.revealed_at,
+            EscrowError::SecretRevealedTooLate
+        );
+
+        self.revealed_part = Some(self.revealed_part.map_or(secret_index, |part| part.max(secret_index)));
+        Ok(())
+    }
+
+    /// Whether the fills withdrawn so far already reached the part that secret `secret_index` unlocks.
+    fn has_withdrawn_part(&self, secret_index: u16) -> bool {
+        let filled = (self.amount - self.remaining_amount) as u128;
+        filled > 0 && (filled - 1) * self.parts_amount as u128 / self.amount as u128 >= secret_index as u128
+    }
+
+    /// Whether an accepted order reached cancellation through its resolver's fault: a secret was public on
+    /// Solana before the public withdrawal window opened, leaving the resolver all of it to withdraw the part
+    /// it unlocks, or a single-fill order bound for Solana was never filled there. `secret_revealed_at` comes
+    /// from the secret registry entry of a single-fill hashlock; partial-fill orders use `revealed_part`
+    /// instead. `destination_filled` comes from the destination hashlock nullifier.
+    pub fn resolver_at_fault(&self, secret_revealed_at: Option<i64>, destination_filled: bool) -> Result<bool> {
+        let revealed_in_time = if self.parts_amount > 1 {
+            self.revealed_part.is_some_and(|part| !self.has_withdrawn_part(part))
+        } else {
+            match secret_revealed_at {
+                Some(revealed_at) => revealed_at < Timelocks::deadline(self.creation_timestamp, self.timelocks.public_withdrawal)?,
+                None => false,
+            }
+        };
+        // Partial-fill orders can't be filled on Solana at all (see `check_source_order`)
+        let never_filled = self.parts_amount == 1 && self.destination.chain_id == SOLANA_CHAIN_ID && !destination_filled;
+        Ok(self.is_accepted && (revealed_in_time || never_filled))
+    }
+
+    /// Portion of the safety deposit still backing `remaining` tokens of the order.
+    pub fn safety_deposit_for(&self, remaining: u64) -> Result<u64> {
+        let share = (self.safety_deposit_sol as u128)
+            .checked_mul(remaining as u128)
+            .ok_or(EscrowError::Overflow)?
+            / self.amount as u128;
+        Ok(share as u64)
+    }
+}
+
+
+#[account]
+pub struct IntentNonce {
+    pub maker: Pubkey,
+    pub nonce: u64,
+    pub order_id: [u8; 32],
+}
+
+impl IntentNonce {
+    // 8 (discriminator) + 32 (maker) + 8 (nonce) + 32 (order_id)
+    pub const LEN: usize = 8 + 32 + 8 + 32;
+}
+
+#[account]
+pub struct OrderStateDestination {
+    pub order_id: [u8; 32],
+    pub order_id_bound: bool,
+    pub maker_on_source: Pubkey, // Payout recipient when maker_evm_address is set
+    pub maker_evm_address: Option<[u8; 20]>,
+    pub resolver: Pubkey,
+    pub rent_payer: Pubkey, // The resolver, who funded the fill; gets all rent back whatever the outcome
+    pub token_mint_destination: Pubkey, // Pubkey::default() for native SOL orders
+    pub amount_on_destination: u64,
+    pub safety_deposit_sol: u64,
+    pub hash_secret: [u8; 32],
+    pub hash_algorithm: HashAlgorithm,
+    pub creation_timestamp: i64,
+    pub timelocks: Timelocks,
+    pub source: SourceCommitment,
+    pub source_cancellation_timestamp: i64, // At least `min_safety_margin` after this order's cancellation deadline; unverified for non-Solana sources
+    pub public_cancel_reward_bps: u16,
+    pub auction: AuctionDetails,
+    pub protocol_fee_bps: u16, // Config rate at fill time
+    pub metadata: Option<DonationMetadata>,
+    pub integrator_fee: Option<IntegratorFee>,
+    pub is_native: bool, // Amount is held as lamports in this PDA instead of an escrow token account
+    pub is_withdrawn_by_maker: bool,
+    pub is_cancelled_by_resolver: bool,
+    pub escrow_authority_bump: u8,
+}
+
+impl OrderStateDestination {
+    pub fn nullifier(&self, order: Pubkey, outcome: OrderOutcome, secret: Option<[u8; 32]>, closed_at: i64) -> Nullifier {
+        Nullifier {
+            order,
+            order_id: self.order_id,
+            hash_secret: self.hash_secret,
+            outcome,
+            secret,
+            closed_at,
+            bump: 0, // Set per nullifier account by `write_nullifiers`
+        }
+    }
+
+    // 8 (discriminator) + 32 (order_id) + 1 (order_id_bound) + 32 (maker_on_source) + 21 (maker_evm_address)
+    // + 32 (resolver) + 32 (rent_payer) + 32 (token_mint) + 8 (amount) + 8 (safety_deposit) + 32 (hash_secret) + HashAlgorithm::LEN + 8 (creation_ts) + Timelocks::LEN
+    // + SourceCommitment::LEN + 8 (source_cancellation_timestamp) + 2 (public_cancel_reward_bps) + AuctionDetails::LEN + 2 (protocol_fee_bps)
+    // + 1 + DonationMetadata::LEN (metadata) + 1 + IntegratorFee::LEN (integrator_fee)
+    // + 1 (is_native) + 1 (is_withdrawn) + 1 (is_cancelled) + 1 (escrow_authority_bump)
+    pub const LEN: usize = 8 + 32 + 1 + 32 + 21 + 32 + 32 + 32 + 8 + 8 + 32 + HashAlgorithm::LEN + 8 + Timelocks::LEN
+        + SourceCommitment::LEN + 8 + 2 + AuctionDetails::LEN + 2 + 1 + DonationMetadata::LEN + 1 + IntegratorFee::LEN
+        + 1 + 1 + 1 + 1;
+
+    pub fn open(
+        &mut self,
+        resolver: Pubkey,
+        terms: DestinationOrderTerms,
+        protocol_fee_bps: u16,
+        creation_timestamp: i64,
+        is_native: bool,
+        escrow_authority_bump: u8,
+    ) {
+        self.order_id = terms.order_id;
+        self.order_id_bound = terms.order_id_bound;
+        self.maker_on_source = terms.maker_on_source;
+        self.maker_evm_address = terms.maker_evm_address;
+        self.resolver = resolver;
+        self.rent_payer = resolver;
+        self.token_mint_destination = terms.token_mint_destination;
+        self.amount_on_destination = terms.amount_on_destination;
+        self.safety_deposit_sol = terms.safety_deposit_sol;
+        self.hash_secret = terms.hash_secret;
+        self.hash_algorithm = terms.hash_algorithm;
+        self.creation_timestamp = creation_timestamp; // Timestamp when filled on dest
+        self.timelocks = terms.timelocks;
+        self.source = terms.source;
+        self.source_cancellation_timestamp = terms.source_cancellation_timestamp;
+        self.public_cancel_reward_bps = terms.public_cancel_reward_bps;
+        self.auction = terms.auction;
+        self.protocol_fee_bps = protocol_fee_bps;
+        self.metadata = terms.metadata;
+        self.integrator_fee = terms.integrator_fee;
+        self.is_native = is_native;
+        self.is_withdrawn_by_maker = false;
+        self.is_cancelled_by_resolver = false;
+        self.escrow_authority_bump = escrow_authority_bump;
+    }
+
+    pub fn filled_event(&self) -> OrderFilledDestination {
+        OrderFilledDestination {
+            order_id: self.order_id,
+            order_id_bound: self.order_id_bound,
+            maker_on_source: self.maker_on_source,
+            maker_evm_address: self.maker_evm_address,
+            resolver: self.resolver,
+            rent_payer: self.rent_payer,
+            token_mint_destination: self.token_mint_destination,
+            amount_on_destination: self.amount_on_destination,
+            safety_deposit_sol: self.safety_deposit_sol,
+            hash_secret: self.hash_secret,
+            hash_algorithm: self.hash_algorithm,
+            creation_timestamp: self.creation_timestamp,
+            timelocks: self.timelocks,
+            source: self.source.clone(),
+            source_cancellation_timestamp: self.source_cancellation_timestamp,
+            public_cancel_reward_bps: self.public_cancel_reward_bps,
+            auction: self.auction.clone(),
+            protocol_fee_bps: self.protocol_fee_bps,
+            metadata: self.metadata.clone(),
+            integrator_fee: self.integrator_fee,
+            is_native: self.is_native,
+        }
+    }
+
+    /// Checks a maker withdrawal against the schedule, the maker's authorization and the secret, then books it.
+    pub fn apply_withdrawal(
+        &mut self,
+        order: Pubkey,
+        caller: Pubkey,
+        instructions_sysvar: Option<&AccountInfo>,
+        secret: &[u8; 32],
+        current_timestamp: i64,
+    ) -> Result<()> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        if stage == OrderStage::ExclusiveWithdrawal {
+            match self.maker_evm_address {
+                // EVM makers authorize the withdrawal with a secp256k1 signature; anyone may submit it
+                Some(maker_evm_address) => {
+                    let instructions_sysvar = instructions_sysvar.ok_or(EscrowError::MissingSignatureInstruction)?;
+                    verify_secp256k1_signature(instructions_sysvar, &maker_evm_address, &self.evm_withdrawal_message(order))?;
+                }
+                // Solana makers withdraw themselves
+                None => require_keys_eq!(caller, self.maker_on_source, EscrowError::CallerNotMaker),
+            }
+        }
+        self.apply_relayed_withdrawal(secret, current_timestamp)
+    }
+
+    /// Checks a withdrawal that needs no authorization beyond the secret, because the payout can only reach
+    /// the maker, then books it.
+    pub fn apply_relayed_withdrawal(&mut self, secret: &[u8; 32], current_timestamp: i64) -> Result<()> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        require!(stage != OrderStage::FinalityLock, EscrowError::FinalityLockActive);
+        require!(stage.is_withdrawal(), EscrowError::TimeoutExpired);
+        require!(!self.is_withdrawn_by_maker, EscrowError::AlreadyWithdrawnByMaker);
+        require!(!self.is_cancelled_by_resolver, EscrowError::AlreadyCancelledByResolver);
+
+        let provided_hash = self.hash_algorithm.hash(secret);
+        require!(provided_hash == self.hash_secret, EscrowError::InvalidSecret);
+
+        self.is_withdrawn_by_maker = true;
+        Ok(())
+    }
+
+    /// Whether this order is the destination leg of `source_order`, as checked by `check_source_order` at fill time.
+    pub fn mirrors(&self, source_order: &Account<OrderStateSource>) -> bool {
+        self.source.chain_id == SOLANA_CHAIN_ID
+            && self.source.escrow == source_order.key().as_ref()
+            && self.order_id == source_order.order_id
+            && self.hash_secret == source_order.hash_secret
+    }
+
+    /// Books the withdrawal half of `atomic_settle`, which skips the finality lock like its source leg.
+    pub fn apply_atomic_settlement(&mut self, secret: &[u8; 32], current_timestamp: i64) -> Result<()> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        require!(!stage.is_cancellation(), EscrowError::TimeoutExpired);
+        require!(!self.is_withdrawn_by_maker, EscrowError::AlreadyWithdrawnByMaker);
+        require!(!self.is_cancelled_by_resolver, EscrowError::AlreadyCancelledByResolver);
+        require!(self.hash_algorithm.hash(secret) == self.hash_secret, EscrowError::InvalidSecret);
+
+        self.is_withdrawn_by_maker = true;
+        Ok(())
+    }
+
+    /// Checks a cancellation against the schedule and books it.
+    /// Returns the part of the safety deposit owed to a public canceller.
+    pub fn apply_cancellation(&mut self, canceller: Pubkey, current_timestamp: i64) -> Result<u64> {
+        let stage = self.timelocks.stage_at(self.creation_timestamp, current_timestamp)?;
+        require!(stage.is_cancellation(), EscrowError::TimeoutNotExpired);
+        if stage == OrderStage::ExclusiveCancellation {
+            require_keys_eq!(canceller, self.resolver, EscrowError::CallerNotResolver);
+        }
+        require!(!self.is_withdrawn_by_maker, EscrowError::AlreadyWithdrawnByMaker);
+        require!(!self.is_cancelled_by_resolver, EscrowError::AlreadyCancelledByResolver);
+
+        self.is_cancelled_by_resolver = true;
+
+        if stage == OrderStage::PublicCancellation {
+            bps_share(self.safety_deposit_sol, self.public_cancel_reward_bps)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Message an EVM maker signs (EIP-191 personal_sign) to authorize withdrawal to `maker_on_source`. It names
+    /// the `order` account and its resolver, so the signature can't be replayed on another fill of the same id.
+    pub fn evm_withdrawal_message(&self, order: Pubkey) -> Vec<u8> {
+        let mut payload = EVM_WITHDRAWAL_DOMAIN.to_vec();
+        payload.extend_from_slice(crate::ID.as_ref());
+        payload.extend_from_slice(order.as_ref());
+        payload.extend_from_slice(&self.order_id);
+        payload.extend_from_slice(self.maker_on_source.as_ref());
+        payload.extend_from_slice(self.resolver.as_ref());
+        let mut message = format!("\x19Ethereum Signed Message:\n{}", payload.len()).into_bytes();
+        message.extend_from_slice(&payload);
+        message
+    }
+}
+
+// --- Order Terms ---
+
+/// Everything the maker commits to when opening a source order.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SourceOrderTerms {
+    pub order_id: [u8; 32],
+    pub order_id_bound: bool, // order_id must equal `order_id_for` over these terms
+    pub resolver: Pubkey,
+    pub token_mint_source: Pubkey,
+    pub amount: u64,
+    pub hash_secret: [u8; 32],
+    pub hash_algorithm: HashAlgorithm,
+    pub parts_amount: u16,
+    pub timelocks: Timelocks,
+    pub safety_deposit_sol: u64,
+    pub public_cancel_reward_bps: u16,
+    pub auction: AuctionDetails,
+    pub destination: DestinationDescriptor,
+    pub metadata: Option<DonationMetadata>,
+    pub integrator_fee: Option<IntegratorFee>,
+}
+
+impl SourceOrderTerms {
+    pub fn validate(&self) -> Result<()> {
+        require_gt!(self.amount, 0, EscrowError::ZeroAmount);
+        require_gt!(self.safety_deposit_sol, 0, EscrowError::ZeroAmount);
+        require!(self.parts_amount > 0 && u64::from(self.parts_amount) <= self.amount, EscrowError::InvalidPartsAmount);
+        require_gte!(BPS_DENOMINATOR, self.public_cancel_reward_bps, EscrowError::InvalidBasisPoints);
+        self.timelocks.validate()?;
+        self.auction.validate()?;
+        self.destination.validate()?;
+        if let Some(metadata) = &self.metadata {
+            metadata.validate()?;
+        }
+        // The curve is denominated in destination units, so it must never drop below the maker's floor
+        require_gte!(self.auction.end_amount, self.destination.min_amount, EscrowError::InvalidDestinationDescriptor);
+        Ok(())
+    }
+
+    pub fn check_order_id(&self, maker: &Pubkey) -> Result<()> {
+        if self.order_id_bound {
+            let expected =
+                order_id_for(maker, &self.hash_secret, self.hash_algorithm, &self.auction, &self.commitment(), &self.destination, &self.metadata)?;
+            require!(self.order_id == expected, EscrowError::OrderIdMismatch);
+        }
+        Ok(())
+    }
+
+    /// What a destination fill mirroring this order commits to. The escrow is left empty: it is derived from the
+    /// order id, so it is not one of the hashed terms.
+    fn commitment(&self) -> SourceCommitment {
+        SourceCommitment {
+            chain_id: SOLANA_CHAIN_ID,
+            escrow: Vec::new(),
+            token: self.token_mint_source.to_bytes().to_vec(),
+            amount: self.amount,
+            timelocks: self.timelocks,
+        }
+    }
+}
+
+/// Everything the resolver commits to when filling a destination order.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DestinationOrderTerms {
+    pub order_id: [u8; 32],
+    pub order_id_bound: bool, // Taken from the source order, or forced when the source leg is on another chain
+    pub maker_on_source: Pubkey,
+    pub maker_evm_address: Option<[u8; 20]>,
+    pub token_mint_destination: Pubkey,
+    pub amount_on_destination: u64,
+    pub hash_secret: [u8; 32],
+    pub hash_algorithm: HashAlgorithm,
+    pub timelocks: Timelocks,
+    pub source: SourceCommitment,
+    pub source_cancellation_timestamp: i64, // Source order's creation timestamp plus its `cancellation` offset
+    pub safety_deposit_sol: u64,
+    pub public_cancel_reward_bps: u16,
+    pub auction: AuctionDetails,
+    pub destination: DestinationDescriptor, // The source order's descriptor this fill has to satisfy
+    pub metadata: Option<DonationMetadata>,
+    pub integrator_fee: Option<IntegratorFee>,
+}
+
+impl DestinationOrderTerms {
+    pub fn validate(&self, current_timestamp: i64) -> Result<()> {
+        require_gt!(self.amount_on_destination, 0, EscrowError::ZeroAmount);
+        require_gt!(self.safety_deposit_sol, 0, EscrowError::ZeroAmount);
+        require_gte!(BPS_DENOMINATOR, self.public_cancel_reward_bps, EscrowError::InvalidBasisPoints);
+        self.timelocks.validate()?;
+        self.auction.validate()?;
+        self.source.validate()?;
+        if let Some(metadata) = &self.metadata {
+            metadata.validate()?;
+        }
+        require_gte!(
+            self.amount_on_destination,
+            self.auction.amount_at(current_timestamp),
+            EscrowError::AmountBelowAuctionCurve
+        );
+        Ok(())
+    }
+
+    /// The fill must pay what the maker asked for: this chain, this mint, to the maker, on the maker's schedule,
+    /// minus no integrator fee other than the maker's.
+    pub fn check_descriptor(&self, decimals: u8) -> Result<()> {
+        let destination = &self.destination;
+        require!(
+            destination.chain_id == SOLANA_CHAIN_ID
+                && destination.token == self.token_mint_destination.as_ref()
+                && destination.recipient == self.maker_on_source.as_ref()
+                && destination.decimals == decimals
+                && destination.timelocks == self.timelocks
+                && destination.integrator_fee == self.integrator_fee
+                && self.amount_on_destination >= destination.min_amount,
+            EscrowError::DestinationDescriptorMismatch
+        );
+        Ok(())
+    }
+
+    /// A bound order_id proves these terms are the ones the maker committed to on the source chain.
+    pub fn check_order_id(&self) -> Result<()> {
+        if self.order_id_bound {
+            let expected = order_id_for(
+                &self.maker_on_source,
+                &self.hash_secret,
+                self.hash_algorithm,
+                &self.auction,
+                &self.source,
+                &self.destination,
+                &self.metadata,
+            )?;
+            require!(self.order_id == expected, EscrowError::OrderIdMismatch);
+        }
+        Ok(())
+    }
+
+    /// When the source leg lives on Solana too, the resolver must pass it: an open source order with the same
+    /// order id, hashlock, schedule, auction curve and destination descriptor, escrowing what this fill commits to.
+    /// Partial-fill source orders are locked to a Merkle root, which no single destination escrow could be
+    /// withdrawn against, so they can't be filled on Solana.
+    pub fn check_source_order(&self, source_order: Option<&Account<OrderStateSource>>) -> Result<()> {
+        let Some(source_order) = source_order else {
+            require_neq!(self.source.chain_id, SOLANA_CHAIN_ID, EscrowError::MissingSourceOrder);
+            return Ok(());
+        };
+        require_eq!(source_order.parts_amount, 1, EscrowError::PartialSourceOrder);
+        require!(
+            self.source.chain_id == SOLANA_CHAIN_ID
+                && self.source.escrow == source_order.key().as_ref()
+                && self.source.token == source_order.token_mint_source.as_ref()
+                && self.source.amount == source_order.amount
+                && self.order_id == source_order.order_id
+                && self.hash_secret == source_order.hash_secret
+                && self.hash_algorithm == source_order.hash_algorithm
+                && self.source.timelocks == source_order.timelocks
+                && self.source_cancellation_timestamp
+                    == Timelocks::deadline(source_order.creation_timestamp, source_order.timelocks.cancellation)?
+                && self.auction == source_order.auction
+                && self.destination == source_order.destination
+                && self.metadata == source_order.metadata,
+            EscrowError::SourceOrderMismatch
+        );
+        require!(!source_order.is_withdrawn, EscrowError::AlreadyWithdrawn);
+        require!(!source_order.is_cancelled, EscrowError::AlreadyCancelled);
+        Ok(())
+    }
+}
+
+/// Order id derived from every term both chains see identically: the maker, the hashlock, the destination
+/// auction curve, the source leg (chain, token, escrowed amount and schedule), the destination descriptor and the
+/// donation metadata. A fill that changes any of them cannot reuse the order_id, so destination events only show
+/// donations the maker attached. `source.escrow` is skipped: escrow addresses are themselves derived from the
+/// order id, so they are bound through it.
+pub fn order_id_for(
+    maker: &Pubkey,
+    hash_secret: &[u8; 32],
+    hash_algorithm: HashAlgorithm,
+    auction: &AuctionDetails,
+    source: &SourceCommitment,
+    destination: &DestinationDescriptor,
+    metadata: &Option<DonationMetadata>,
+) -> Result<[u8; 32]> {
+    let mut payload = ORDER_ID_DOMAIN.to_vec();
+    payload.extend_from_slice(maker.as_ref());
+    payload.extend_from_slice(hash_secret);
+    hash_algorithm.serialize(&mut payload)?;
+    auction.serialize(&mut payload)?;
+    source.chain_id.serialize(&mut payload)?;
+    source.token.serialize(&mut payload)?;
+    source.amount.serialize(&mut payload)?;
+    source.timelocks.serialize(&mut payload)?;
+    destination.serialize(&mut payload)?;
+    metadata.serialize(&mut payload)?;
+    Ok(anchor_lang::solana_program::keccak::hash(&payload).to_bytes())
+}
+
+/// Fills of a Solana source order are checked against the order account itself and keep the maker's choice. Fills of
+/// orders on other chains can only prove their terms through the order id, so binding is mandatory for them.
+fn order_id_bound_for(source_order: Option<&Account<OrderStateSource>>) -> bool {
+    source_order.map(|order| order.order_id_bound).unwrap_or(true)
+}
+
+/// What the maker expects to receive on the destination chain, so resolvers and auditors can check fills against
+/// chain data alone. Foreign addresses are kept as raw bytes in the destination chain's native encoding.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DestinationDescriptor {
+    pub chain_id: u64,
+    pub token: Vec<u8>, // On Solana, the mint, or the all-zero key for native SOL
+    pub recipient: Vec<u8>,
+    pub min_amount: u64, // In the destination token's base units
+    pub decimals: u8,
+    pub timelocks: Timelocks, // Schedule the destination fill must use
+    pub integrator_fee: Option<IntegratorFee>, // The only integrator fee a fill may take from the maker's payout
+}
+
+impl DestinationDescriptor {
+    // 8 (chain_id) + 4 + MAX_FOREIGN_ADDRESS_LEN (token) + 4 + MAX_FOREIGN_ADDRESS_LEN (recipient) + 8 (min_amount) + 1 (decimals)
+    // + Timelocks::LEN (timelocks) + 1 + IntegratorFee::LEN (integrator_fee)
+    pub const LEN: usize = 8 + 4 + MAX_FOREIGN_ADDRESS_LEN + 4 + MAX_FOREIGN_ADDRESS_LEN + 8 + 1 + Timelocks::LEN + 1 + IntegratorFee::LEN;
+
+    pub fn validate(&self) -> Result<()> {
+        let is_address = |bytes: &[u8]| !bytes.is_empty() && bytes.len() <= MAX_FOREIGN_ADDRESS_LEN;
+        require!(
+            self.chain_id > 0 && is_address(&self.token) && is_address(&self.recipient) && self.min_amount > 0,
+            EscrowError::InvalidDestinationDescriptor
+        );
+        self.timelocks.validate()
+    }
+}
+
+/// The source escrow a destination fill mirrors, so auditors can match both legs. Addresses are raw bytes in the
+/// source chain's native encoding; for Solana source orders `escrow` is the order PDA and `token` the mint.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceCommitment {
+    pub chain_id: u64,
+    pub escrow: Vec<u8>,
+    pub token: Vec<u8>,
+    pub amount: u64, // Escrowed on the source chain, in the source token's base units
+    pub timelocks: Timelocks, // Source order's schedule
+}
+
+impl SourceCommitment {
+    // 8 (chain_id) + 4 + MAX_FOREIGN_ADDRESS_LEN (escrow) + 4 + MAX_FOREIGN_ADDRESS_LEN (token) + 8 (amount)
+    // + Timelocks::LEN (timelocks)
+    pub const LEN: usize = 8 + 4 + MAX_FOREIGN_ADDRESS_LEN + 4 + MAX_FOREIGN_ADDRESS_LEN + 8 + Timelocks::LEN;
+
+    pub fn validate(&self) -> Result<()> {
+        let is_address = |bytes: &[u8]| !bytes.is_empty() && bytes.len() <= MAX_FOREIGN_ADDRESS_LEN;
+        require!(
+            self.chain_id > 0 && is_address(&self.escrow) && is_address(&self.token) && self.amount > 0,
+            EscrowError::InvalidSourceCommitment
+        );
+        self.timelocks.validate()
+    }
+}
+
+/// Donation made through a payment link, kept with the order and echoed into its events so the creator dashboard
+/// can render messages from chain data alone. `version` lets the layout evolve; only the current one is accepted.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DonationMetadata {
+    pub version: u8,
+    pub link_id: String,
+    pub message: String, // Borsh rejects strings that are not valid UTF-8
+    pub donor_name: Option<String>, // Display name, None for anonymous donations
+}
+
+impl DonationMetadata {
+    // 1 (version) + 4 + MAX_LINK_ID_LEN (link_id) + 4 + MAX_DONATION_MESSAGE_LEN (message) + 1 + 4 + MAX_DONOR_NAME_LEN (donor_name)
+    pub const LEN: usize = 1 + 4 + MAX_LINK_ID_LEN + 4 + MAX_DONATION_MESSAGE_LEN + 1 + 4 + MAX_DONOR_NAME_LEN;
+
+    pub fn validate(&self) -> Result<()> {
+        require_eq!(self.version, DONATION_METADATA_VERSION, EscrowError::InvalidDonationMetadata);
+        require!(
+            !self.link_id.is_empty()
+                && self.link_id.len() <= MAX_LINK_ID_LEN
+                && self.message.len() <= MAX_DONATION_MESSAGE_LEN
+                && self.donor_name.iter().all(|name| !name.is_empty() && name.len() <= MAX_DONOR_NAME_LEN),
+            EscrowError::InvalidDonationMetadata
+        );
+        Ok(())
+    }
+}
+
+/// Fee an integrator (e.g. a payment-link frontend) takes on settlement, on top of the protocol fee.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntegratorFee {
+    pub recipient: Pubkey, // Owner of the fee token account, or the lamport receiver for native orders
+    pub fee_bps: u16,
+}
+
+impl IntegratorFee {
+    pub const LEN: usize = 32 + 2;
+}
+
+/// Off-chain order signed by the maker with Ed25519 and submitted by the resolver.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OrderIntent {
+    pub maker: Pubkey,
+    pub terms: SourceOrderTerms,
+    pub expiry: i64, // Unix timestamp after which the intent can no longer be submitted
+    pub nonce: u64,
+}
+
+impl OrderIntent {
+    /// Bytes the maker signs: domain tag, program id, then the Borsh-encoded intent.
+    pub fn signing_message(&self) -> Result<Vec<u8>> {
+        let mut message = ORDER_INTENT_DOMAIN.to_vec();
+        message.extend_from_slice(crate::ID.as_ref());
+        self.serialize(&mut message)?;
+        Ok(message)
+    }
+}
+
+// --- Signature Verification ---
+
+// Checks that the instruction right before the current one is an Ed25519 program instruction verifying
+// `message` signed by `signer`, with all data carried inline in that instruction.
+fn verify_ed25519_signature(instructions_sysvar: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let ix = instructions_sysvar::get_instruction_relative(-1, instructions_sysvar)
+        .map_err(|_| error!(EscrowError::MissingSignatureInstruction))?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, EscrowError::MissingSignatureInstruction);
+
+    // Layout: num_signatures (u8), padding (u8), then one Ed25519SignatureOffsets of seven u16 values
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, EscrowError::InvalidSignatureInstruction);
+    let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let signature_instruction_index = read_u16(4);
+    let public_key_offset = read_u16(6) as usize;
+    let public_key_instruction_index = read_u16(8);
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    let message_instruction_index = read_u16(14);
+    require!(
+        signature_instruction_index == u16::MAX
+            && public_key_instruction_index == u16::MAX
+            && message_instruction_index == u16::MAX,
+        EscrowError::InvalidSignatureInstruction
+    );
+
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_size);
+    require!(public_key == Some(signer.as_ref()), EscrowError::InvalidSignatureInstruction);
+    require!(signed_message == Some(message), EscrowError::InvalidSignatureInstruction);
+    Ok(())
+}
+
+// Checks that the instruction right before the current one is a secp256k1 program instruction recovering
+// `eth_address` as the signer of `message`, with all data carried inline in that instruction.
+fn verify_secp256k1_signature(instructions_sysvar: &AccountInfo, eth_address: &[u8; 20], message: &[u8]) -> Result<()> {
+    let current_index = instructions_sysvar::load_current_index_checked(instructions_sysvar)?;
+    require_gt!(current_index, 0, EscrowError::MissingSignatureInstruction);
+    let ix_index = current_index - 1;
+    let ix = instructions_sysvar::load_instruction_at_checked(ix_index as usize, instructions_sysvar)?;
+    require_keys_eq!(ix.program_id, secp256k1_program::ID, EscrowError::MissingSignatureInstruction);
+
+    // Layout: num_signatures (u8), then one SecpSignatureOffsets of u16/u8 offsets and instruction indexes
+    let data = &ix.data;
+    require!(data.len() >= 12 && data[0] == 1, EscrowError::InvalidSignatureInstruction);
+    let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]) as usize;
+    let signature_instruction_index = data[3];
+    let eth_address_offset = read_u16(4);
+    let eth_address_instruction_index = data[6];
+    let message_offset = read_u16(7);
+    let message_size = read_u16(9);
+    let message_instruction_index = data[11];
+    require!(
+        [signature_instruction_index, eth_address_instruction_index, message_instruction_index]
+            .iter()
+            .all(|index| u16::from(*index) == ix_index),
+        EscrowError::InvalidSignatureInstruction
+    );
+
+    let recovered_address = data.get(eth_address_offset..eth_address_offset + 20);
+    let signed_message = data.get(message_offset..message_offset + message_size);
+    require!(recovered_address == Some(eth_address.as_ref()), EscrowError::InvalidSignatureInstruction);
+    require!(signed_message == Some(message), EscrowError::InvalidSignatureInstruction);
+    Ok(())
+}
+
+// --- Hashlock ---
+
+/// Hash applied to a revealed secret before it is compared with the hashlock. SHA-256 variants let the same
+/// secret unlock Bitcoin / Lightning HTLCs, which commit to SHA-256 payment hashes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    #[default]
+    Keccak256,
+    Sha256,
+    DoubleSha256, // sha256(sha256(secret)), as in Bitcoin's HASH256
+}
+
+impl HashAlgorithm {
+    pub const LEN: usize = 1;
+
+    pub fn hash(self, secret: &[u8]) -> [u8; 32] {
+        match self {
+            HashAlgorithm::Keccak256 => anchor_lang::solana_program::keccak::hash(secret).to_bytes(),
+            HashAlgorithm::Sha256 => anchor_lang::solana_program::hash::hash(secret).to_bytes(),
+            HashAlgorithm::DoubleSha256 => {
+                let first = anchor_lang::solana_program::hash::hash(secret).to_bytes();
+                anchor_lang::solana_program::hash::hash(&first).to_bytes()
+            }
+        }
+    }
+}
+
+// --- Timelocks ---
+
+/// Fusion+ style schedule. Each value is an offset in seconds from the order's creation timestamp at which
+/// the stage starts; the finality lock runs from creation until `withdrawal`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timelocks {
+    pub withdrawal: u32, // End of the finality lock, start of the exclusive withdrawal window
+    pub public_withdrawal: u32,
+    pub cancellation: u32, // Start of the exclusive cancellation window
+    pub public_cancellation: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderStage {
+    FinalityLock,
+    ExclusiveWithdrawal,
+    PublicWithdrawal,
+    ExclusiveCancellation,
+    PublicCancellation,
+}
+
+impl Timelocks {
+    pub const LEN: usize = 4 + 4 + 4 + 4;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            0 < self.withdrawal
+                && self.withdrawal < self.public_withdrawal
+                && self.public_withdrawal < self.cancellation
+                && self.cancellation < self.public_cancellation,
+            EscrowError::InvalidTimelocks
+        );
+        Ok(())
+    }
+
+    /// Absolute timestamp at which the stage starting `offset` seconds after creation begins.
+    pub fn deadline(creation_timestamp: i64, offset: u32) -> Result<i64> {
+        creation_timestamp.checked_add(i64::from(offset)).ok_or_else(|| error!(EscrowError::Overflow))
+    }
+
+    pub fn stage_at(&self, creation_timestamp: i64, current_timestamp: i64) -> Result<OrderStage> {
+        let reached = |offset: u32| -> Result<bool> { Ok(current_timestamp >= Self::deadline(creation_timestamp, offset)?) };
+        let stage = if !reached(self.withdrawal)? {
+            OrderStage::FinalityLock
+        } else if !reached(self.public_withdrawal)? {
+            OrderStage::ExclusiveWithdrawal
+        } else if !reached(self.cancellation)? {
+            OrderStage::PublicWithdrawal
+        } else if !reached(self.public_cancellation)? {
+            OrderStage::ExclusiveCancellation
+        } else {
+            OrderStage::PublicCancellation
+        };
+        Ok(stage)
+    }
+}
+
+impl OrderStage {
+    pub fn is_withdrawal(self) -> bool {
+        matches!(self, OrderStage::ExclusiveWithdrawal | OrderStage::PublicWithdrawal)
+    }
+
+    pub fn is_cancellation(self) -> bool {
+        matches!(self, OrderStage::ExclusiveCancellation | OrderStage::PublicCancellation)
+    }
+}
+
+// --- Dutch Auction ---
+
+/// Decreasing price curve for the destination amount, committed by the maker when the order is created.
+/// The curve runs linearly through `start_amount`, each point, and `end_amount` at `start_timestamp + duration`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuctionDetails {
+    pub start_amount: u64,
+    pub end_amount: u64,
+    pub start_timestamp: i64,
+    pub duration: u32,
+    pub points: Vec<AuctionPoint>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AuctionPoint {
+    pub delay: u32, // Seconds after the auction start
+    pub amount: u64,
+}
+
+impl AuctionDetails {
+    // 8 (start_amount) + 8 (end_amount) + 8 (start_timestamp) + 4 (duration) + 4 (vec len) + points
+    pub const LEN: usize = 8 + 8 + 8 + 4 + 4 + MAX_AUCTION_POINTS * (4 + 8);
+
+    pub fn validate(&self) -> Result<()> {
+        require!(self.duration > 0 && self.points.len() <= MAX_AUCTION_POINTS, EscrowError::InvalidAuction);
+        let mut previous = AuctionPoint { delay: 0, amount: self.start_amount };
+        for point in self.nodes() {
+            require!(point.delay > previous.delay, EscrowError::InvalidAuction);
+            require_gte!(previous.amount, point.amount, EscrowError::InvalidAuction);
+            previous = point;
+        }
+        Ok(())
+    }
+
+    /// Minimum destination amount accepted at `timestamp`.
+    pub fn amount_at(&self, timestamp: i64) -> u64 {
+        let elapsed = timestamp.saturating_sub(self.start_timestamp);
+        if elapsed <= 0 {
+            return self.start_amount;
+        }
+        let mut previous = AuctionPoint { delay: 0, amount: self.start_amount };
+        for point in self.nodes() {
+            if elapsed < point.delay as i64 {
+                let span = (point.delay - previous.delay) as u128;
+                let progress = (elapsed - previous.delay as i64) as u128;
+                let drop = (previous.amount - point.amount) as u128 * progress / span;
+                return previous.amount - drop as u64;
+            }
+            previous = point;
+        }
+        self.end_amount
+    }
+
+    // Intermediate points followed by the end of the auction
+    fn nodes(&self) -> impl Iterator<Item = AuctionPoint> + '_ {
+        self.points.iter().copied().chain(std::iter::once(AuctionPoint {
+            delay: self.duration,
+            amount: self.end_amount,
+        }))
+    }
+}
+
+// --- Deposit Helpers ---
+
+/// `bps` basis points of `amount`, rounded down.
+pub fn bps_share(amount: u64, bps: u16) -> Result<u64> {
+    let share = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(EscrowError::Overflow)?
+        / BPS_DENOMINATOR as u128;
+    Ok(share as u64)
+}
+
+// Moves lamports out of a program-owned PDA without dipping into its rent-exempt reserve.
+fn transfer_lamports_from_pda(pda: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let rent_reserve = Rent::get()?.minimum_balance(pda.data_len());
+    require_gte!(pda.lamports().saturating_sub(amount), rent_reserve, EscrowError::InsufficientEscrowBalance);
+    **pda.try_borrow_mut_lamports()? -= amount;
+    **to.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+// --- Token Helpers ---
+
+// Moves tokens with `transfer_checked`, forwarding `extra_accounts` (a transfer-hook mint's extra account metas,
+// hook program and validation account) so Token-2022 can invoke the hook. Returns what the recipient actually
+// received, which is less than `amount` for mints with a transfer fee.
+#[allow(clippy::too_many_arguments)]
+fn transfer_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    extra_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<u64> {
+    let balance_before = to.amount;
+
+    let mut ix = spl_token_2022::instruction::transfer_checked(
+        token_program.key,
+        &from.key(),
+        &mint.key(),
+        &to.key(),
+        authority.key,
+        &[],
+        amount,
+        mint.decimals,
+    )?;
+    ix.accounts.extend(extra_accounts.iter().map(|account| AccountMeta {
+        pubkey: *account.key,
+        is_signer: account.is_signer,
+        is_writable: account.is_writable,
+    }));
+
+    let mut account_infos = vec![from.to_account_info(), mint.to_account_info(), to.to_account_info(), authority];
+    account_infos.extend_from_slice(extra_accounts);
+    anchor_lang::solana_program::program::invoke_signed(&ix, &account_infos, signer_seeds)?;
+
+    to.reload()?;
+    Ok(to.amount.checked_sub(balance_before).ok_or(EscrowError::Overflow)?)
+}
+
+// Token-2022 refuses to close an account still holding withheld transfer fees, so those are harvested into the
+// mint first (harvesting is permissionless). Remaining rent goes to `destination`.
+fn close_escrow_token_account<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    escrow_token_account: &InterfaceAccount<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if token_interface::get_mint_extension_data::<TransferFeeConfig>(&mint_info).is_ok() {
+        let harvest_accounts = HarvestWithheldTokensToMint {
+            token_program_id: token_program.to_account_info(),
+            mint: mint_info,
+        };
+        token_interface::harvest_withheld_tokens_to_mint(
+            CpiContext::new(token_program.to_account_info(), harvest_accounts),
+            vec![escrow_token_account.to_account_info()],
+        )?;
+    }
 
-    #[account(mut)]
-    pub resolver: Signer<'info>, // Resolver initiates this
+    let close_accounts = CloseAccount {
+        account: escrow_token_account.to_account_info(),
+        destination,
+        authority,
+    };
+    token_interface::close_account(CpiContext::new_with_signer(token_program.to_account_info(), close_accounts, signer_seeds))
+}
 
-    #[account(mut)]
-    pub resolver_token_account_source: Account<'info, TokenAccount>,
+// --- Staking Helpers ---
 
-    #[account(
-        mut,
-        token::mint = order_state_source.token_mint_source,
-        token::authority = escrow_authority,
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref(), b"token_source"],
-        bump, // We might need to store this bump on OrderStateSource if not derivable or pass it
-        close = resolver // Close escrow token account and send lamports to resolver
-    )]
-    pub escrow_token_account_source: Account<'info, TokenAccount>,
+// Slashes the resolver behind an accepted source order that ran into cancellation through its own fault (see
+// `OrderStateSource::resolver_at_fault`). The maker receives `maker_compensation_bps` of the slash, the treasury
+// the rest. A maker who withholds the secret from a funded destination cannot trigger it.
+fn slash_resolver<'info>(
+    order: &OrderStateSource,
+    staking: &StakingParams,
+    secret_record: &AccountInfo<'info>,
+    destination_hashlock_nullifier: &AccountInfo<'info>,
+    resolver_stake: Option<&mut Account<'info, ResolverStake>>,
+    treasury: Option<&SystemAccount<'info>>,
+    maker: &AccountInfo<'info>,
+) -> Result<()> {
+    let destination_filled = !destination_hashlock_nullifier.data_is_empty();
+    if !order.resolver_at_fault(secret_revealed_at(secret_record)?, destination_filled)? {
+        return Ok(());
+    }
+    let (Some(resolver_stake), Some(treasury)) = (resolver_stake, treasury) else {
+        return err!(EscrowError::MissingResolverStake);
+    };
 
-    /// CHECK: PDA authority for the escrow token account.
-    #[account(
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref()],
-        bump = order_state_source.escrow_authority_bump
-    )]
-    pub escrow_authority: UncheckedAccount<'info>,
-    
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    let slashed_amount = resolver_stake.slash(staking.slash_amount);
+    let maker_compensation = bps_share(slashed_amount, staking.maker_compensation_bps)?;
+    let stake_info = resolver_stake.to_account_info();
+    transfer_lamports_from_pda(&stake_info, maker, maker_compensation)?;
+    transfer_lamports_from_pda(&stake_info, &treasury.to_account_info(), slashed_amount - maker_compensation)?;
+
+    emit!(ResolverSlashed {
+        order_id: order.order_id,
+        resolver: order.resolver,
+        slashed_amount,
+        maker_compensation,
+    });
+    Ok(())
 }
 
-#[derive(Accounts)]
-#[instruction(order_id: [u8; 32])]
-pub struct CancelSourceAccounts<'info> {
-    #[account(
-        mut,
-        seeds = [ORDER_PDA_SEED_PREFIX_SOURCE, order_id.as_ref()],
-        bump,
-        has_one = maker, // Ensures funds go back to original maker
-        close = maker // Close order_state_source account and send lamports to maker
-    )]
-    pub order_state_source: Account<'info, OrderStateSource>,
+// Reveal time of the preimage registered in `secret_record`, a secret registry PDA that may not exist yet.
+fn secret_revealed_at(secret_record: &AccountInfo) -> Result<Option<i64>> {
+    if secret_record.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*secret_record.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+    let record = SecretRecord::try_deserialize(&mut &secret_record.try_borrow_data()?[..])?;
+    Ok(record.is_registered().then_some(record.revealed_at))
+}
 
-    #[account(mut)]
-    pub maker: SystemAccount<'info>, // Receiver of SOL, not necessarily signer if anyone can cancel
+// Lamports held by accounts that are about to close, i.e. the rent returned to the order's rent payer.
+fn rent_to_refund(accounts: &[AccountInfo]) -> Result<u64> {
+    accounts
+        .iter()
+        .try_fold(0u64, |total, account| total.checked_add(account.lamports()))
+        .ok_or_else(|| error!(EscrowError::Overflow))
+}
 
-    #[account(mut)]
-    pub maker_token_account_source: Account<'info, TokenAccount>, // Receiver of tokens
+// --- Fee Helpers ---
 
-    #[account(mut)]
-    pub canceller: Signer<'info>, // Anyone can call cancel after timeout
+/// How a settled amount is divided between its recipient and the fee receivers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SettlementSplit {
+    pub payout: u64,
+    pub protocol_fee: u64,
+    pub integrator_fee: u64,
+}
 
-    #[account(
-        mut,
-        token::mint = order_state_source.token_mint_source,
-        token::authority = escrow_authority,
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref(), b"token_source"],
-        bump,
-        close = maker // Close escrow token account and send lamports to maker
-    )]
-    pub escrow_token_account_source: Account<'info, TokenAccount>,
+pub fn split_settlement(amount: u64, protocol_fee_bps: u16, integrator_fee: Option<IntegratorFee>) -> Result<SettlementSplit> {
+    let protocol_fee = bps_share(amount, protocol_fee_bps)?;
+    let integrator_fee = match integrator_fee {
+        Some(integrator_fee) => bps_share(amount, integrator_fee.fee_bps)?,
+        None => 0,
+    };
+    let payout = amount
+        .checked_sub(protocol_fee)
+        .and_then(|rest| rest.checked_sub(integrator_fee))
+        .ok_or(EscrowError::Overflow)?;
+    Ok(SettlementSplit {
+        payout,
+        protocol_fee,
+        integrator_fee,
+    })
+}
 
-    /// CHECK: PDA authority.
-    #[account(
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref()],
-        bump = order_state_source.escrow_authority_bump
-    )]
-    pub escrow_authority: UncheckedAccount<'info>,
+// Sends a non-zero fee from the escrow token account; the fee account must then be provided.
+#[allow(clippy::too_many_arguments)]
+fn pay_fee_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    escrow_token_account: &InterfaceAccount<'info, TokenAccount>,
+    fee_account: Option<&mut InterfaceAccount<'info, TokenAccount>>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    extra_accounts: &[AccountInfo<'info>],
+    fee: u64,
+) -> Result<()> {
+    if fee == 0 {
+        return Ok(());
+    }
+    let fee_account = fee_account.ok_or(EscrowError::MissingFeeAccount)?;
+    transfer_tokens(token_program, mint, escrow_token_account, fee_account, authority, signer_seeds, extra_accounts, fee)?;
+    Ok(())
+}
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+// Sends a non-zero fee in lamports from a native order PDA; the receiver must then be provided.
+fn pay_fee_lamports(order: &AccountInfo, receiver: Option<&SystemAccount>, fee: u64) -> Result<()> {
+    if fee == 0 {
+        return Ok(());
+    }
+    let receiver = receiver.ok_or(EscrowError::MissingFeeAccount)?;
+    transfer_lamports_from_pda(order, &receiver.to_account_info(), fee)
 }
 
+// --- Nullifier Helpers ---
 
-#[derive(Accounts)]
-#[instruction(order_id: [u8; 32])]
-pub struct FillOrderDestinationAccounts<'info> {
-    #[account(
-        init,
-        payer = resolver,
-        space = OrderStateDestination::LEN,
-        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_id.as_ref()],
-        bump
-    )]
-    pub order_state_destination: Account<'info, OrderStateDestination>,
+// Claims the hashlock for a new order. The account already existing (another order, open or closed, used this
+// hashlock on the same chain side) fails the `init`, so no second order can be stranded behind the first one's tombstone.
+fn reserve_hashlock(nullifier: &mut Account<Nullifier>, record: Nullifier, bump: u8) {
+    nullifier.set_inner(Nullifier { bump, ..record });
+}
 
-    #[account(mut)]
-    pub resolver: Signer<'info>,
+// Writes the same tombstone to the new order nullifier and over the hashlock reservation of a closing order.
+fn write_nullifiers(
+    record: Nullifier,
+    (order_nullifier, order_nullifier_bump): (&mut Account<Nullifier>, u8),
+    hashlock_nullifier: &mut Account<Nullifier>,
+) {
+    let hashlock_nullifier_bump = hashlock_nullifier.bump;
+    order_nullifier.set_inner(Nullifier { bump: order_nullifier_bump, ..record.clone() });
+    hashlock_nullifier.set_inner(Nullifier { bump: hashlock_nullifier_bump, ..record });
+}
 
-    #[account(mut)]
-    pub resolver_token_account_destination: Account<'info, TokenAccount>,
-    
-    #[account(
-        init,
-        payer = resolver,
-        token::mint = token_mint_destination,
-        token::authority = escrow_authority,
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref(), b"token_dest"], // Unique seed
-        bump
-    )]
-    pub escrow_token_account_destination: Account<'info, TokenAccount>,
+// --- Partial Fill Helpers ---
 
-    /// CHECK: PDA authority.
-    #[account(
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref()], // Same authority PDA for both sides of the order_id
-        bump
-    )]
-    pub escrow_authority: UncheckedAccount<'info>,
+// Partial fills follow the 1inch Fusion+ scheme: an order split into N parts commits to N + 1 secrets.
+// Secret `i` unlocks the fill that brings the cumulative filled amount into the i-th N-th of the order,
+// and the extra secret `N` is reserved for the fill that completes it.
+pub fn is_valid_partial_fill(
+    order_amount: u64,
+    remaining_amount: u64,
+    parts_amount: u16,
+    fill_amount: u64,
+    secret_index: u16,
+) -> bool {
+    let (order_amount, remaining_amount, fill_amount) = (order_amount as u128, remaining_amount as u128, fill_amount as u128);
+    let parts_amount = parts_amount as u128;
+    let filled_after = order_amount - remaining_amount + fill_amount;
+    let calculated_index = (filled_after - 1) * parts_amount / order_amount;
 
-    pub token_mint_destination: Account<'info, Mint>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    if remaining_amount == fill_amount {
+        // Completing the order always consumes the extra secret after the calculated one
+        return secret_index as u128 == calculated_index + 1;
+    }
+    if remaining_amount != order_amount {
+        // A later fill has to move into a new part, otherwise its secret was already revealed
+        let previous_index = (order_amount - remaining_amount - 1) * parts_amount / order_amount;
+        if calculated_index == previous_index {
+            return false;
+        }
+    }
+    secret_index as u128 == calculated_index
 }
 
-#[derive(Accounts)]
-#[instruction(order_id: [u8; 32])]
-pub struct WithdrawDestinationAccounts<'info> {
-    #[account(
-        mut,
-        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_id.as_ref()],
-        bump,
-        // has_one = maker_on_source, // This check is tricky if maker_on_source is not a Solana Pubkey directly usable as signer
-        close = maker_on_destination_chain_signer
-    )]
-    pub order_state_destination: Account<'info, OrderStateDestination>,
-
-    #[account(mut)]
-    pub maker_on_destination_chain_signer: Signer<'info>, // The one signing this transaction, assumed to be the maker
+// Leaves are keccak256(uint64 index || secret hash), matching `abi.encodePacked` on the EVM side.
+pub fn merkle_leaf(secret_index: u16, secret_hash: &[u8; 32]) -> [u8; 32] {
+    anchor_lang::solana_program::keccak::hashv(&[&u64::from(secret_index).to_be_bytes(), secret_hash]).to_bytes()
+}
 
-    #[account(mut)]
-    pub maker_token_account_destination: Account<'info, TokenAccount>,
+// Nodes hash their children in sorted order, so proofs carry no left/right flags (OpenZeppelin compatible).
+fn merkle_parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    anchor_lang::solana_program::keccak::hashv(&[first, second]).to_bytes()
+}
 
-    #[account(
-        mut,
-        token::mint = order_state_destination.token_mint_destination,
-        token::authority = escrow_authority,
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref(), b"token_dest"],
-        bump,
-        close = maker_on_destination_chain_signer
-    )]
-    pub escrow_token_account_destination: Account<'info, TokenAccount>,
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    proof.iter().fold(leaf, |node, sibling| merkle_parent(&node, sibling)) == root
+}
 
-    /// CHECK: PDA authority.
-    #[account(
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref()],
-        bump = order_state_destination.escrow_authority_bump
-    )]
-    pub escrow_authority: UncheckedAccount<'info>,
+// Off-chain helpers for makers and resolvers building the secret tree.
+// `secret_hashes[i]` is secret `i` hashed with the order's `HashAlgorithm`; an order with N parts needs N + 1 of them.
+// Tree nodes are always keccak256.
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+pub fn merkle_leaves(secret_hashes: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    secret_hashes
+        .iter()
+        .enumerate()
+        .map(|(index, hash)| merkle_leaf(index as u16, hash))
+        .collect()
 }
 
-#[derive(Accounts)]
-#[instruction(order_id: [u8; 32])]
-pub struct CancelDestinationAccounts<'info> {
-    #[account(
-        mut,
-        seeds = [ORDER_PDA_SEED_PREFIX_DESTINATION, order_id.as_ref()],
-        bump,
-        has_one = resolver,
-        close = resolver
-    )]
-    pub order_state_destination: Account<'info, OrderStateDestination>,
+fn merkle_next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => merkle_parent(a, b),
+            [a] => *a, // An odd node is carried up unchanged
+            _ => unreachable!(),
+        })
+        .collect()
+}
 
-    #[account(mut)]
-    pub resolver: Signer<'info>, // Resolver cancels and gets funds back
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = merkle_next_level(&level);
+    }
+    level.first().copied().unwrap_or_default()
+}
 
-    #[account(mut)]
-    pub resolver_token_account_destination: Account<'info, TokenAccount>,
+pub fn merkle_proof(leaves: &[[u8; 32]], leaf_index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut index = leaf_index;
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = merkle_next_level(&level);
+        index /= 2;
+    }
+    proof
+}
 
-    #[account(
-        mut,
-        token::mint = order_state_destination.token_mint_destination,
-        token::authority = escrow_authority,
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref(), b"token_dest"],
-        bump,
-        close = resolver
-    )]
-    pub escrow_token_account_destination: Account<'info, TokenAccount>,
+// --- Events ---
 
-    /// CHECK: PDA authority.
-    #[account(
-        seeds = [ESCROW_AUTHORITY_SEED_PREFIX, order_id.as_ref()],
-        bump = order_state_destination.escrow_authority_bump
-    )]
-    pub escrow_authority: UncheckedAccount<'info>,
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub protocol_fee_bps: u16,
+    pub relayer_reward_bps: u16,
+    pub paused: bool,
+    pub limits: ProtocolLimits,
+    pub staking: StakingParams,
+}
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct AdminTransferProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
 }
 
+#[event]
+pub struct AdminTransferred {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
 
-// --- State Structs ---
+#[event]
+pub struct ResolverStatusChanged {
+    pub resolver: Pubkey,
+    pub status: ResolverStatus,
+}
 
-#[account]
-pub struct OrderStateSource {
-    pub order_id: [u8; 32],
-    pub maker: Pubkey,
+#[event]
+pub struct StakeDeposited {
     pub resolver: Pubkey,
-    pub token_mint_source: Pubkey,
     pub amount: u64,
-    pub safety_deposit_sol: u64,
-    pub hash_secret: [u8; 32],
-    pub creation_timestamp: i64,
-    pub timeout_duration_seconds: i64,
-    pub is_withdrawn: bool,
-    pub is_cancelled: bool,
-    pub escrow_authority_bump: u8, // To sign for escrow token account
+    pub staked_amount: u64,
 }
 
-impl OrderStateSource {
-    // 8 (discriminator) + 32 (order_id) + 32 (maker) + 32 (resolver) + 32 (token_mint) + 8 (amount)
-    // + 8 (safety_deposit) + 32 (hash_secret) + 8 (creation_ts) + 8 (timeout_duration)
-    // + 1 (is_withdrawn) + 1 (is_cancelled) + 1 (escrow_authority_bump)
-    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 32 + 8 + 8 + 1 + 1 + 1;
+#[event]
+pub struct UnstakeRequested {
+    pub resolver: Pubkey,
+    pub amount: u64,
+    pub available_at: i64,
 }
 
+#[event]
+pub struct StakeWithdrawn {
+    pub resolver: Pubkey,
+    pub amount: u64,
+}
 
-#[account]
-pub struct OrderStateDestination {
+#[event]
+pub struct OrderAcceptedSource {
     pub order_id: [u8; 32],
-    pub maker_on_source: Pubkey,
     pub resolver: Pubkey,
-    pub token_mint_destination: Pubkey,
-    pub amount_on_destination: u64,
-    pub safety_deposit_sol: u64,
-    pub hash_secret: [u8; 32],
-    pub creation_timestamp: i64,
-    pub timeout_duration_seconds: i64,
-    pub is_withdrawn_by_maker: bool,
-    pub is_cancelled_by_resolver: bool,
-    pub escrow_authority_bump: u8,
+    pub staked_amount: u64,
 }
 
-impl OrderStateDestination {
-    // 8 (discriminator) + 32 (order_id) + 32 (maker_on_source) + 32 (resolver) + 32 (token_mint)
-    // + 8 (amount) + 8 (safety_deposit) + 32 (hash_secret) + 8 (creation_ts) + 8 (timeout_duration)
-    // + 1 (is_withdrawn) + 1 (is_cancelled) + 1 (escrow_authority_bump)
-    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 32 + 8 + 8 + 1 + 1 + 1;
+#[event]
+pub struct RevealedSecretReported {
+    pub order_id: [u8; 32],
+    pub resolver: Pubkey,
+    pub secret_index: u16,
+    pub revealed_at: i64,
 }
 
-// --- Events ---
+#[event]
+pub struct ResolverSlashed {
+    pub order_id: [u8; 32],
+    pub resolver: Pubkey,
+    pub slashed_amount: u64,
+    pub maker_compensation: u64,
+}
 
 #[event]
 pub struct OrderCreatedSource {
     pub order_id: [u8; 32],
+    pub order_id_bound: bool,
     pub maker: Pubkey,
     pub resolver: Pubkey,
+    pub rent_payer: Pubkey,
     pub token_mint_source: Pubkey,
     pub amount: u64,
     pub safety_deposit_sol: u64,
     pub hash_secret: [u8; 32],
-    pub timeout_timestamp: i64,
+    pub hash_algorithm: HashAlgorithm,
+    pub parts_amount: u16,
+    pub creation_timestamp: i64,
+    pub timelocks: Timelocks,
+    pub public_cancel_reward_bps: u16,
+    pub auction: AuctionDetails,
+    pub destination: DestinationDescriptor,
+    pub protocol_fee_bps: u16,
+    pub metadata: Option<DonationMetadata>,
+    pub integrator_fee: Option<IntegratorFee>,
+    pub is_native: bool,
 }
 
 #[event]
 pub struct OrderWithdrawnSource {
     pub order_id: [u8; 32],
     pub resolver: Pubkey,
+    pub caller: Pubkey,
     pub secret: [u8; 32],
+    pub secret_index: u16,
+    pub fill_amount: u64,
+    pub received_amount: u64, // What reached the resolver, net of fees and any Token-2022 transfer fee
+    pub protocol_fee: u64,
+    pub integrator_fee: u64,
+    pub remaining_amount: u64,
+    pub deposit_released: u64, // Safety deposit share paid to `caller`
+    pub rent_payer: Pubkey,
+    pub rent_refunded: u64, // Non-zero once the last fill closes the order
 }
 
 #[event]
 pub struct OrderCancelledSource {
     pub order_id: [u8; 32],
     pub canceller: Pubkey,
+    pub refunded_amount: u64,
+    pub keeper_reward: u64,
+    pub deposit_refunded: u64, // Unreleased safety deposit, keeper_reward included
+    pub rent_payer: Pubkey,
+    pub rent_refunded: u64,
 }
 
 #[event]
 pub struct OrderFilledDestination {
     pub order_id: [u8; 32],
+    pub order_id_bound: bool, // Fill terms are the ones the maker committed to
     pub maker_on_source: Pubkey,
+    pub maker_evm_address: Option<[u8; 20]>,
     pub resolver: Pubkey,
+    pub rent_payer: Pubkey,
     pub token_mint_destination: Pubkey,
     pub amount_on_destination: u64,
     pub safety_deposit_sol: u64,
     pub hash_secret: [u8; 32],
-    pub timeout_timestamp: i64,
+    pub hash_algorithm: HashAlgorithm,
+    pub creation_timestamp: i64,
+    pub timelocks: Timelocks,
+    pub source: SourceCommitment,
+    pub source_cancellation_timestamp: i64,
+    pub public_cancel_reward_bps: u16,
+    pub auction: AuctionDetails,
+    pub protocol_fee_bps: u16,
+    pub metadata: Option<DonationMetadata>,
+    pub integrator_fee: Option<IntegratorFee>,
+    pub is_native: bool,
 }
 
 #[event]
 pub struct MakerWithdrawnDestination {
     pub order_id: [u8; 32],
     pub maker: Pubkey, // maker_on_source
+    pub maker_evm_address: Option<[u8; 20]>,
+    pub caller: Pubkey,
     pub secret: [u8; 32],
+    pub received_amount: u64, // What reached the maker, net of fees and any Token-2022 transfer fee
+    pub protocol_fee: u64,
+    pub integrator_fee: u64,
+    pub deposit_released: u64, // Whole safety deposit, relayer_reward included
+    pub rent_payer: Pubkey,
+    pub rent_refunded: u64,
+    pub relayer_reward: u64, // Safety deposit share paid to `caller` by a relayed withdrawal
+    pub metadata: Option<DonationMetadata>,
 }
 
 #[event]
 pub struct OrderCancelledByResolverDestination {
     pub order_id: [u8; 32],
     pub resolver: Pubkey,
+    pub canceller: Pubkey,
+    pub keeper_reward: u64,
+    pub deposit_refunded: u64, // Whole safety deposit, keeper_reward included
+    pub rent_payer: Pubkey,
+    pub rent_refunded: u64,
 }
 
 
@@ -708,4 +4895,367 @@ pub enum EscrowError {
     InsufficientEscrowBalance,
     #[msg("An arithmetic overflow occurred.")]
     Overflow, // Anchor handles this by default with checked-cfg
+    #[msg("Parts amount must be between 1 and the order amount.")]
+    InvalidPartsAmount,
+    #[msg("The fill amount does not match the secret index for this order.")]
+    InvalidPartialFill,
+    #[msg("The Merkle proof does not match the order's secret root.")]
+    InvalidMerkleProof,
+    #[msg("Timelock stages must be strictly increasing and start after the order is created.")]
+    InvalidTimelocks,
+    #[msg("The order is still within its finality lock.")]
+    FinalityLockActive,
+    #[msg("The caller is not allowed to act on the order in its current stage.")]
+    StageRestricted,
+    #[msg("Basis points cannot exceed 10000.")]
+    InvalidBasisPoints,
+    #[msg("Auction curve must be non-increasing with at most 8 points inside its duration.")]
+    InvalidAuction,
+    #[msg("The destination amount is below the auction curve at the current time.")]
+    AmountBelowAuctionCurve,
+    #[msg("The order intent has expired.")]
+    IntentExpired,
+    #[msg("The signature verification instruction must directly precede this instruction.")]
+    MissingSignatureInstruction,
+    #[msg("The signature verification instruction does not match the expected signer and message.")]
+    InvalidSignatureInstruction,
+    #[msg("Use the native SOL instructions for native orders and the SPL token instructions for token orders.")]
+    OrderKindMismatch,
+    #[msg("Only the program upgrade authority can initialize the protocol config.")]
+    NotUpgradeAuthority,
+    #[msg("The signer is not the protocol admin.")]
+    NotAdmin,
+    #[msg("The signer is not the pending protocol admin.")]
+    NotPendingAdmin,
+    #[msg("The protocol is paused; new orders are not accepted.")]
+    ProtocolPaused,
+    #[msg("Protocol limits are inconsistent.")]
+    InvalidProtocolLimits,
+    #[msg("The safety deposit is below the protocol minimum.")]
+    SafetyDepositTooLow,
+    #[msg("The order exceeds a protocol limit.")]
+    ExceedsProtocolLimit,
+    #[msg("The resolver is not registered as active.")]
+    ResolverNotActive,
+    #[msg("The resolver's stake is insufficient.")]
+    InsufficientStake,
+    #[msg("Unstaked funds are still in their cooldown period.")]
+    UnstakeCooldownActive,
+    #[msg("Stake is locked until the resolver's accepted orders reach public cancellation.")]
+    StakeLocked,
+    #[msg("The order has already been accepted by its resolver.")]
+    AlreadyAccepted,
+    #[msg("The resolver stake and treasury accounts are required to cancel an accepted order.")]
+    MissingResolverStake,
+    #[msg("The secret was revealed after public withdrawal opened, so it is no evidence against the resolver.")]
+    SecretRevealedTooLate,
+    #[msg("A fee is due but its fee account was not provided.")]
+    MissingFeeAccount,
+    #[msg("The fee account does not belong to the order's fee recipient.")]
+    InvalidFeeAccount,
+    #[msg("This order id was already used by a closed order.")]
+    AlreadyNullified,
+    #[msg("Nullifier accounts must belong to the order and be provided exactly with the fill that completes it.")]
+    NullifierMismatch,
+    #[msg("No secret has been registered for this hashlock yet.")]
+    SecretNotRegistered,
+    #[msg("Rent refunds go only to the account that paid the order's rent.")]
+    InvalidRentPayer,
+    #[msg("The order timeout is outside the bounds set by the protocol.")]
+    TimeoutOutOfBounds,
+    #[msg("The destination order must stop accepting withdrawals well before the source order does.")]
+    InsufficientSafetyMargin,
+    #[msg("The destination descriptor is incomplete, too long, or below the auction curve.")]
+    InvalidDestinationDescriptor,
+    #[msg("The source commitment is incomplete or its addresses are too long.")]
+    InvalidSourceCommitment,
+    #[msg("Fills of Solana source orders must pass the source order account.")]
+    MissingSourceOrder,
+    #[msg("The source order does not match the destination fill.")]
+    SourceOrderMismatch,
+    #[msg("Partial-fill source orders cannot be filled on Solana.")]
+    PartialSourceOrder,
+    #[msg("Donation metadata has an unknown version, an empty link id, or a field over its size limit.")]
+    InvalidDonationMetadata,
+    #[msg("Order id does not match the hash of the order parameters.")]
+    OrderIdMismatch,
+    #[msg("The fill does not pay out the chain, token, recipient, schedule or minimum amount the maker asked for.")]
+    DestinationDescriptorMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::keccak;
+
+    fn secret_hashes(count: u8) -> Vec<[u8; 32]> {
+        (0..count).map(|i| keccak::hash(&[i]).to_bytes()).collect()
+    }
+
+    // --- Order Ids ---
+
+    fn timelocks() -> Timelocks {
+        Timelocks { withdrawal: 1, public_withdrawal: 600, cancellation: 1_200, public_cancellation: 1_800 }
+    }
+
+    fn order_id(source: &SourceCommitment, destination: &DestinationDescriptor, metadata: &Option<DonationMetadata>) -> [u8; 32] {
+        let auction = AuctionDetails { start_amount: 100, end_amount: 90, start_timestamp: 0, duration: 60, points: vec![] };
+        order_id_for(&Pubkey::new_from_array([7; 32]), &[1; 32], HashAlgorithm::Keccak256, &auction, source, destination, metadata).unwrap()
+    }
+
+    #[test]
+    fn order_id_binds_every_immutable_term() {
+        let source = SourceCommitment { chain_id: 1, escrow: vec![0xe5; 20], token: vec![0xaa; 20], amount: 1_000, timelocks: timelocks() };
+        let destination = DestinationDescriptor {
+            chain_id: SOLANA_CHAIN_ID,
+            token: vec![2; 32],
+            recipient: vec![7; 32],
+            min_amount: 90,
+            decimals: 6,
+            timelocks: timelocks(),
+            integrator_fee: None,
+        };
+        let id = order_id(&source, &destination, &None);
+
+        let later = Timelocks { public_cancellation: 3_600, ..timelocks() };
+        let changed_sources = [
+            SourceCommitment { chain_id: 10, ..source.clone() },
+            SourceCommitment { token: vec![0xbb; 20], ..source.clone() },
+            SourceCommitment { amount: 999, ..source.clone() },
+            SourceCommitment { timelocks: later, ..source.clone() },
+        ];
+        for changed in &changed_sources {
+            assert_ne!(order_id(changed, &destination, &None), id);
+        }
+        let changed_destinations = [
+            DestinationDescriptor { token: vec![3; 32], ..destination.clone() },
+            DestinationDescriptor { recipient: vec![8; 32], ..destination.clone() },
+            DestinationDescriptor { min_amount: 1, ..destination.clone() },
+            DestinationDescriptor { decimals: 9, ..destination.clone() },
+            DestinationDescriptor { timelocks: later, ..destination.clone() },
+            DestinationDescriptor {
+                integrator_fee: Some(IntegratorFee { recipient: Pubkey::new_from_array([9; 32]), fee_bps: 50 }),
+                ..destination.clone()
+            },
+        ];
+        for changed in &changed_destinations {
+            assert_ne!(order_id(&source, changed, &None), id);
+        }
+
+        // A resolver can't attach a donation the maker didn't sign or edit one it did
+        let metadata = DonationMetadata { version: 1, link_id: "link".to_string(), message: "gm".to_string(), donor_name: None };
+        let with_metadata = order_id(&source, &destination, &Some(metadata.clone()));
+        assert_ne!(with_metadata, id);
+        let edited = DonationMetadata { donor_name: Some("resolver".to_string()), ..metadata };
+        assert_ne!(order_id(&source, &destination, &Some(edited)), with_metadata);
+
+        // The escrow address is derived from the order id, so it cannot be part of it
+        assert_eq!(order_id(&SourceCommitment { escrow: vec![0xe6; 20], ..source.clone() }, &destination, &None), id);
+    }
+
+    // --- Dutch Auction ---
+
+    #[test]
+    fn auction_amount_follows_the_curve() {
+        let auction = AuctionDetails {
+            start_amount: 3_000,
+            end_amount: 1_000,
+            start_timestamp: 1_000,
+            duration: 100,
+            points: vec![AuctionPoint { delay: 50, amount: 1_500 }],
+        };
+        assert!(auction.validate().is_ok());
+        assert_eq!(auction.amount_at(900), 3_000); // Not started yet
+        assert_eq!(auction.amount_at(1_000), 3_000);
+        assert_eq!(auction.amount_at(1_025), 2_250); // Halfway to the first point
+        assert_eq!(auction.amount_at(1_050), 1_500);
+        assert_eq!(auction.amount_at(1_075), 1_250);
+        assert_eq!(auction.amount_at(1_100), 1_000);
+        assert_eq!(auction.amount_at(5_000), 1_000); // Ended
+    }
+
+    #[test]
+    fn auction_curve_must_not_rise() {
+        let rising = AuctionDetails {
+            start_amount: 1_000,
+            end_amount: 1_000,
+            start_timestamp: 0,
+            duration: 100,
+            points: vec![AuctionPoint { delay: 50, amount: 2_000 }],
+        };
+        assert!(rising.validate().is_err());
+    }
+
+    // --- Slashing ---
+
+    fn accepted_order(destination_chain_id: u64, parts_amount: u16) -> OrderStateSource {
+        OrderStateSource {
+            order_id: [1; 32],
+            order_id_bound: true,
+            maker: Pubkey::new_from_array([7; 32]),
+            resolver: Pubkey::new_from_array([8; 32]),
+            rent_payer: Pubkey::new_from_array([7; 32]),
+            token_mint_source: Pubkey::new_from_array([2; 32]),
+            amount: 1_000,
+            remaining_amount: 1_000,
+            safety_deposit_sol: 0,
+            hash_secret: [1; 32],
+            hash_algorithm: HashAlgorithm::Keccak256,
+            parts_amount,
+            creation_timestamp: 10_000,
+            timelocks: timelocks(),
+            public_cancel_reward_bps: 0,
+            auction: AuctionDetails::default(),
+            destination: DestinationDescriptor { chain_id: destination_chain_id, ..DestinationDescriptor::default() },
+            protocol_fee_bps: 0,
+            metadata: None,
+            integrator_fee: None,
+            is_native: false,
+            is_accepted: true,
+            revealed_part: None,
+            is_withdrawn: false,
+            is_cancelled: true,
+            escrow_authority_bump: 255,
+        }
+    }
+
+    #[test]
+    fn withheld_secret_does_not_fault_the_resolver() {
+        // The resolver filled on Solana and the secret never became public
+        assert!(!accepted_order(SOLANA_CHAIN_ID, 1).resolver_at_fault(None, true).unwrap());
+        assert!(!accepted_order(1, 1).resolver_at_fault(None, false).unwrap());
+    }
+
+    #[test]
+    fn unfilled_solana_destination_faults_the_resolver() {
+        assert!(accepted_order(SOLANA_CHAIN_ID, 1).resolver_at_fault(None, false).unwrap());
+        // Partial-fill orders can't be filled on Solana, so not filling them there is no fault
+        assert!(!accepted_order(SOLANA_CHAIN_ID, 4).resolver_at_fault(None, false).unwrap());
+
+        let mut open_order = accepted_order(SOLANA_CHAIN_ID, 1);
+        open_order.is_accepted = false;
+        assert!(!open_order.resolver_at_fault(None, false).unwrap());
+    }
+
+    #[test]
+    fn unused_public_secret_faults_the_resolver() {
+        // Public withdrawal opens 600s after creation at 10_000
+        assert!(accepted_order(1, 1).resolver_at_fault(Some(10_599), true).unwrap());
+        // Revealed too late for the resolver to have the whole public window
+        assert!(!accepted_order(1, 1).resolver_at_fault(Some(10_600), true).unwrap());
+    }
+
+    #[test]
+    fn reported_secret_faults_the_resolver_until_its_part_is_withdrawn() {
+        let secrets: Vec<[u8; 32]> = (0..5u8).map(|i| [i + 1; 32]).collect();
+        let hashes: Vec<[u8; 32]> = secrets.iter().map(|secret| HashAlgorithm::Keccak256.hash(secret)).collect();
+        let leaves = merkle_leaves(&hashes);
+        let mut order = accepted_order(1, 4);
+        order.hash_secret = merkle_root(&leaves);
+        order.is_cancelled = false;
+        let record = |index: usize, revealed_at: i64| SecretRecord {
+            hashlock: hashes[index],
+            hash_algorithm: HashAlgorithm::Keccak256,
+            secret: secrets[index],
+            revealed_at,
+            bump: 255,
+        };
+
+        // Public withdrawal opens at 10_600
+        assert!(order.report_revealed_secret(&record(2, 10_600), 2, &merkle_proof(&leaves, 2)).is_err());
+        assert!(order.report_revealed_secret(&record(2, 10_100), 1, &merkle_proof(&leaves, 1)).is_err());
+        order.report_revealed_secret(&record(2, 10_100), 2, &merkle_proof(&leaves, 2)).unwrap();
+        order.report_revealed_secret(&record(1, 10_100), 1, &merkle_proof(&leaves, 1)).unwrap();
+        assert_eq!(order.revealed_part, Some(2));
+
+        // Secret 2 unlocks the third quarter: withdrawing half of the order is not enough
+        order.remaining_amount = 500;
+        assert!(order.resolver_at_fault(None, true).unwrap());
+        order.remaining_amount = 250;
+        assert!(!order.resolver_at_fault(None, true).unwrap());
+    }
+
+    #[test]
+    fn stake_lock_only_extends() {
+        let mut stake = ResolverStake {
+            resolver: Pubkey::new_from_array([8; 32]),
+            staked_amount: 1_000,
+            pending_unstake_amount: 0,
+            unstake_available_at: 0,
+            locked_until: 0,
+            bump: 255,
+        };
+        stake.lock_until(20_000);
+        // Accepting an order that ends sooner keeps the longer lock
+        stake.lock_until(15_000);
+        assert_eq!(stake.locked_until, 20_000);
+        stake.lock_until(25_000);
+        assert_eq!(stake.locked_until, 25_000);
+    }
+
+    // --- Partial Fill Helpers ---
+
+    #[test]
+    fn first_fill_uses_the_secret_of_its_part() {
+        // 100 tokens in 4 parts: secrets 0..=3 cover each quarter, secret 4 completes the order
+        assert!(is_valid_partial_fill(100, 100, 4, 10, 0));
+        assert!(!is_valid_partial_fill(100, 100, 4, 10, 1));
+        assert!(is_valid_partial_fill(100, 100, 4, 30, 1));
+        assert!(!is_valid_partial_fill(100, 100, 4, 30, 0));
+    }
+
+    #[test]
+    fn middle_fill_moves_into_a_new_part() {
+        assert!(is_valid_partial_fill(100, 90, 4, 20, 1));
+        assert!(is_valid_partial_fill(100, 70, 4, 30, 2));
+        assert!(!is_valid_partial_fill(100, 70, 4, 30, 3));
+    }
+
+    #[test]
+    fn fill_within_an_already_revealed_part_is_rejected() {
+        // The first fill revealed secret 0; another fill inside the first quarter would reuse it
+        assert!(!is_valid_partial_fill(100, 90, 4, 5, 0));
+        assert!(!is_valid_partial_fill(100, 90, 4, 5, 1));
+    }
+
+    #[test]
+    fn completing_fill_uses_the_extra_secret() {
+        assert!(is_valid_partial_fill(100, 30, 4, 30, 4));
+        assert!(!is_valid_partial_fill(100, 30, 4, 30, 3));
+        assert!(is_valid_partial_fill(100, 100, 4, 100, 4));
+        assert!(!is_valid_partial_fill(100, 100, 4, 100, 3));
+    }
+
+    #[test]
+    fn merkle_proofs_round_trip_for_odd_leaf_counts() {
+        for count in [1u8, 3, 5, 7] {
+            let leaves = merkle_leaves(&secret_hashes(count));
+            let root = merkle_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle_proof(&leaves, index);
+                assert!(verify_merkle_proof(&proof, root, *leaf), "leaf {index} of {count}");
+                assert!(!verify_merkle_proof(&proof, root, merkle_leaf(index as u16, &[0xff; 32])));
+            }
+        }
+    }
+
+    #[test]
+    fn merkle_root_carries_odd_nodes_up() {
+        let leaves = merkle_leaves(&secret_hashes(3));
+        assert_eq!(merkle_root(&leaves[..1]), leaves[0]);
+        assert!(merkle_proof(&leaves[..1], 0).is_empty());
+        let expected = merkle_parent(&merkle_parent(&leaves[0], &leaves[1]), &leaves[2]);
+        assert_eq!(merkle_root(&leaves), expected);
+        assert_eq!(merkle_proof(&leaves, 2), vec![merkle_parent(&leaves[0], &leaves[1])]);
+    }
+
+    #[test]
+    fn merkle_leaves_commit_to_the_secret_index() {
+        let hashes = secret_hashes(2);
+        let leaves = merkle_leaves(&hashes);
+        let root = merkle_root(&leaves);
+        let proof = merkle_proof(&leaves, 0);
+        // The right secret hash under the wrong index does not verify
+        assert!(!verify_merkle_proof(&proof, root, merkle_leaf(1, &hashes[0])));
+    }
 }
@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use spl_discriminator::SplDiscriminate;
+use spl_tlv_account_resolution::{account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, InitializeExtraAccountMetaListInstruction};
+
+declare_id!("9srChKL2tUfauXxa6aBncjtEigkDQTGCf6HCJiNHqNbr"); // Placeholder, will be updated after first build
+
+// Test fixture for the escrow's Token-2022 transfer-hook support. The hook asks Token-2022 for one extra
+// account, a per-mint counter PDA, and bumps it on every transfer, so tests can tell the hook actually ran
+// with the accounts the escrow forwarded.
+
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+pub const COUNTER_SEED: &[u8] = b"counter";
+
+#[program]
+pub mod transfer_hook_fixture {
+    use super::*;
+
+    #[instruction(discriminator = InitializeExtraAccountMetaListInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn initialize_extra_account_meta_list(ctx: Context<InitializeExtraAccountMetaList>) -> Result<()> {
+        let mut data = ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?;
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &extra_account_metas()?)?;
+
+        ctx.accounts.counter.bump = ctx.bumps.counter;
+        Ok(())
+    }
+
+    #[instruction(discriminator = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn execute(ctx: Context<Execute>, amount: u64) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.transfers = counter.transfers.checked_add(1).ok_or(HookError::Overflow)?;
+        counter.transferred = counter.transferred.checked_add(amount).ok_or(HookError::Overflow)?;
+        Ok(())
+    }
+}
+
+// Account indexes follow the interface's `Execute` layout: source, mint, destination, owner, validation account.
+fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    Ok(vec![ExtraAccountMeta::new_with_seeds(
+        &[
+            Seed::Literal { bytes: COUNTER_SEED.to_vec() },
+            Seed::AccountKey { index: 1 },
+        ],
+        false, // is_signer
+        true,  // is_writable
+    )?])
+}
+
+// --- Accounts ---
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    /// CHECK: Written as an `ExtraAccountMetaList` TLV, which Anchor does not model
+    #[account(
+        init,
+        payer = payer,
+        space = ExtraAccountMetaList::size_of(1)?,
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = TransferCounter::LEN,
+        seeds = [COUNTER_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub counter: Account<'info, TransferCounter>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Execute<'info> {
+    #[account(token::mint = mint)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Source owner or delegate, already authorized by Token-2022
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: Validation account, only its address matters here
+    #[account(seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    #[account(mut, seeds = [COUNTER_SEED, mint.key().as_ref()], bump = counter.bump)]
+    pub counter: Account<'info, TransferCounter>,
+}
+
+// --- State ---
+
+#[account]
+pub struct TransferCounter {
+    pub transfers: u64,
+    pub transferred: u64,
+    pub bump: u8,
+}
+
+impl TransferCounter {
+    // 8 discriminator + 8 transfers + 8 transferred + 1 bump
+    pub const LEN: usize = 8 + 8 + 8 + 1;
+}
+
+// --- Errors ---
+
+#[error_code]
+pub enum HookError {
+    #[msg("Transfer counter overflowed.")]
+    Overflow,
+}
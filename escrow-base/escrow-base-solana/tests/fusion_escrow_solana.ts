@@ -1,8 +1,38 @@
 import * as anchor from '@coral-xyz/anchor';
 import { Program } from '@coral-xyz/anchor';
 import { FusionEscrowSolana } from '../target/types/fusion_escrow_solana';
-import { Keypair, SystemProgram, PublicKey, LAMPORTS_PER_SOL } from '@solana/web3.js';
-import { TOKEN_PROGRAM_ID, createMint, createAccount, mintTo, getAccount } from '@solana/spl-token';
+import { TransferHookFixture } from '../target/types/transfer_hook_fixture';
+import {
+  Keypair,
+  SystemProgram,
+  PublicKey,
+  LAMPORTS_PER_SOL,
+  Transaction,
+  TransactionInstruction,
+  Ed25519Program,
+  Secp256k1Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  sendAndConfirmTransaction,
+} from '@solana/web3.js';
+import {
+  TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  createMint,
+  createAccount,
+  mintTo,
+  getAccount,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeTransferHookInstruction,
+  addExtraAccountMetasForExecute,
+  approveChecked,
+  getExtraAccountMetaAddress,
+} from '@solana/spl-token';
+import { createHash } from 'crypto';
+import { keccak_256 } from '@noble/hashes/sha3';
+import { secp256k1 } from '@noble/curves/secp256k1';
 import { assert } from 'chai';
 import BN from 'bn.js';
 
@@ -12,6 +42,8 @@ describe('fusion_escrow_solana', () => {
   anchor.setProvider(provider);
 
   const program = anchor.workspace.FusionEscrowSolana as Program<FusionEscrowSolana>;
+  // Minimal transfer-hook program from this workspace, used by the Token-2022 tests
+  const hookProgram = anchor.workspace.TransferHookFixture as Program<TransferHookFixture>;
 
   // Wallets and users
   const maker = provider.wallet as anchor.Wallet; // Use provider's wallet as maker
@@ -38,7 +70,7 @@ describe('fusion_escrow_solana', () => {
 
   before(async () => {
     // Airdrop SOL to resolver and anotherUser for transaction fees
-    await provider.connection.requestAirdrop(resolver.publicKey, 2 * LAMPORTS_PER_SOL);
+    await provider.connection.requestAirdrop(resolver.publicKey, 10 * LAMPORTS_PER_SOL); // Fronts deposits and rent for many fills
     await provider.connection.requestAirdrop(anotherUser.publicKey, 1 * LAMPORTS_PER_SOL);
     await new Promise(resolve => setTimeout(resolve, 1000)); // Wait for airdrop
 
@@ -71,15 +103,15 @@ describe('fusion_escrow_solana', () => {
 
 
     // Derive PDAs
-    // Order State PDA for source chain
+    // Order State PDA for source chain, namespaced by the maker
     [orderStatePdaSource] = PublicKey.findProgramAddressSync(
-        [Buffer.from("order_source"), Buffer.from(orderId)],
+        [Buffer.from("order_source"), maker.publicKey.toBuffer(), Buffer.from(orderId)],
         program.programId
     );
 
-    // Escrow Authority PDA (this PDA will own the escrowed tokens)
+    // Escrow Authority PDA (this PDA will own the escrowed tokens), bound to the order account
     [escrowAuthorityPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("escrow_authority"), Buffer.from(orderId)],
+        [Buffer.from("escrow_authority"), orderStatePdaSource.toBuffer()],
         program.programId
     );
 
@@ -91,6 +123,31 @@ describe('fusion_escrow_solana', () => {
         escrowAuthorityPda // Owner of this token account is the PDA
     );
 
+    // Protocol config, initialized by the program's upgrade authority (the provider wallet on localnet)
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111')
+    );
+    await program.methods
+      .initializeConfig(
+        maker.publicKey,
+        maker.publicKey, // Treasury
+        0, // Protocol fee bps
+        2_500, // Relayer reward bps
+        protocolLimits(),
+        {
+          minStake: new BN(LAMPORTS_PER_SOL / 10),
+          unstakeCooldown: 2,
+          slashAmount: new BN(LAMPORTS_PER_SOL / 20),
+          makerCompensationBps: 5_000,
+        }
+      )
+      .accountsPartial({ authority: maker.publicKey, programData })
+      .rpc();
+
+    // Only registered resolvers can be named in source orders or fill on the destination chain
+    await program.methods.registerResolver(resolver.publicKey).accountsPartial({ admin: maker.publicKey }).rpc();
+
     console.log("Setup complete.");
     console.log("Maker:", maker.publicKey.toBase58());
     console.log("Resolver:", resolver.publicKey.toBase58());
@@ -102,12 +159,480 @@ describe('fusion_escrow_solana', () => {
     console.log("Hash Secret:", Buffer.from(hashSecret).toString('hex'));
   });
 
+  // --- Shared helpers ---
+
+  const decimals = 6;
+  const mintAmount = 1_000_000 * 10 ** 6;
+  const timelocks = { withdrawal: 1, publicWithdrawal: 600, cancellation: 1200, publicCancellation: 1800 };
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  const [configPda] = PublicKey.findProgramAddressSync([Buffer.from('config')], program.programId);
+
+  // Limits set in `before`; suites that walk an order through its stages lower the timeout floor meanwhile
+  const protocolLimits = (minTimeout = 60) => ({
+    minSafetyDepositSol: new BN(0),
+    maxPartsAmount: 64,
+    maxPublicCancelRewardBps: 10_000,
+    maxIntegratorFeeBps: 100,
+    sourceTimeout: { min: minTimeout, max: 7 * 24 * 3_600 },
+    destinationTimeout: { min: minTimeout, max: 3 * 24 * 3_600 },
+    minSafetyMargin: 600, // Destination cancellation must come 10 minutes before the source one
+  });
+
+  // Cancellation opens 6 seconds after creation
+  const quickTimelocks = { withdrawal: 1, publicWithdrawal: 4, cancellation: 6, publicCancellation: 60 };
+
+  const newOrderId = (label: string) => Array.from(Buffer.from(label.padEnd(32, '\0')).subarray(0, 32));
+
+  const sourceOrderPdas = (id: number[], orderMaker = maker.publicKey) => {
+    const [orderState] = PublicKey.findProgramAddressSync(
+      [Buffer.from('order_source'), orderMaker.toBuffer(), Buffer.from(id)],
+      program.programId
+    );
+    const [escrowAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from('escrow_authority'), orderState.toBuffer()],
+      program.programId
+    );
+    const [escrowTokenAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from('escrow_authority'), orderState.toBuffer(), Buffer.from('token_source')],
+      program.programId
+    );
+    return { orderState, escrowAuthority, escrowTokenAccount };
+  };
+
+  const destinationOrderPdas = (id: number[]) => {
+    const [orderState] = PublicKey.findProgramAddressSync(
+      [Buffer.from('order_destination'), resolver.publicKey.toBuffer(), Buffer.from(id)],
+      program.programId
+    );
+    const [escrowAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from('escrow_authority'), orderState.toBuffer()],
+      program.programId
+    );
+    const [escrowTokenAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from('escrow_authority'), orderState.toBuffer(), Buffer.from('token_dest')],
+      program.programId
+    );
+    return { orderState, escrowAuthority, escrowTokenAccount };
+  };
+
+  const flatAuction = () => ({
+    startAmount: new BN(1),
+    endAmount: new BN(1),
+    startTimestamp: new BN(0),
+    duration: 1,
+    points: [],
+  });
+
+  // Maker expects at least flatAuction().endAmount of a 6-decimal ERC-20 on Ethereum mainnet
+  const evmDestination = () => ({
+    chainId: new BN(1),
+    token: Buffer.from('a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48', 'hex'),
+    recipient: Buffer.alloc(20, 0xd0),
+    minAmount: new BN(1),
+    decimals: 6,
+    timelocks,
+    integratorFee: null as { recipient: PublicKey; feeBps: number } | null,
+  });
+
+  // What a maker asks for when paid on Solana: the fill's mint, to the maker, on the fill's schedule, with no integrator fee
+  const solanaDestination = (mint: PublicKey, destinationTimelocks = timelocks, tokenDecimals = decimals) => ({
+    chainId: new BN(501),
+    token: mint.toBuffer(),
+    recipient: maker.publicKey.toBuffer(),
+    minAmount: new BN(1),
+    decimals: tokenDecimals,
+    timelocks: destinationTimelocks,
+    integratorFee: null as { recipient: PublicKey; feeBps: number } | null,
+  });
+
+  // Destination fills here mirror an escrow on Ethereum mainnet unless a test says otherwise
+  const evmSource = (chainId = 1, amount = new BN(10 ** 6)) => ({
+    chainId: new BN(chainId),
+    escrow: Buffer.alloc(20, 0xe5),
+    token: Buffer.from('a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48', 'hex'),
+    amount,
+    timelocks,
+  });
+
+  // keccak256(domain || maker || hashlock || hash algorithm || auction || source chain, token, amount, schedule
+  // || destination descriptor), as in `order_id_for`; the source escrow is not hashed
+  const orderIdFor = (
+    hashSecret: number[] | Buffer,
+    auction: ReturnType<typeof flatAuction>,
+    source: ReturnType<typeof evmSource>,
+    destination: ReturnType<typeof evmDestination>,
+    hashAlgorithm = 0, // Keccak256
+    metadata: { version: number; linkId: string; message: string; donorName: string | null } | null = null
+  ) => {
+    const token = Buffer.from(source.token);
+    const tokenLength = Buffer.alloc(4);
+    tokenLength.writeUInt32LE(token.length);
+    return Array.from(
+      keccak_256(
+        Buffer.concat([
+          Buffer.from('fusion_escrow_solana:order_id'),
+          maker.publicKey.toBuffer(),
+          Buffer.from(hashSecret),
+          Buffer.from([hashAlgorithm]),
+          program.coder.types.encode('AuctionDetails', auction),
+          source.chainId.toArrayLike(Buffer, 'le', 8),
+          tokenLength,
+          token,
+          source.amount.toArrayLike(Buffer, 'le', 8),
+          program.coder.types.encode('Timelocks', source.timelocks),
+          program.coder.types.encode('DestinationDescriptor', destination),
+          // Borsh Option: a tag byte, then the value when present
+          metadata ? Buffer.concat([Buffer.from([1]), program.coder.types.encode('DonationMetadata', metadata)]) : Buffer.from([0]),
+        ])
+      )
+    );
+  };
+
+  // Creates a Token-2022 mint with the given extensions initialized before the mint itself
+  const createMint2022 = async (extensions: ExtensionType[], initExtensions: (mint: PublicKey) => TransactionInstruction[]) => {
+    const mintKeypair = Keypair.generate();
+    const mintLen = getMintLen(extensions);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: maker.publicKey,
+        newAccountPubkey: mintKeypair.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      ...initExtensions(mintKeypair.publicKey),
+      createInitializeMintInstruction(mintKeypair.publicKey, decimals, maker.publicKey, null, TOKEN_2022_PROGRAM_ID)
+    );
+    await sendAndConfirmTransaction(provider.connection, tx, [maker.payer, mintKeypair]);
+    return mintKeypair.publicKey;
+  };
+
+  const fundedAccounts = async (mint: PublicKey) => {
+    const makerAccount = await createAccount(
+      provider.connection,
+      maker.payer,
+      mint,
+      maker.publicKey,
+      undefined, // Associated token accounts, the only payout accounts the program accepts
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const resolverAccount = await createAccount(
+      provider.connection,
+      maker.payer,
+      mint,
+      resolver.publicKey,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      provider.connection,
+      maker.payer,
+      mint,
+      makerAccount,
+      maker.publicKey,
+      mintAmount,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    return { makerAccount, resolverAccount };
+  };
+
+  // --- Order lifecycle helpers ---
+
+  // Suites that walk orders through their stages lower the timeout floor so every stage is seconds away
+  const useQuickTimeouts = () => {
+    before(async () => {
+      await program.methods.updateLimits(protocolLimits(1)).accountsPartial({ config: configPda, admin: maker.publicKey }).rpc();
+    });
+    after(async () => {
+      await program.methods.updateLimits(protocolLimits()).accountsPartial({ config: configPda, admin: maker.publicKey }).rpc();
+    });
+  };
+
+  // Token-2022 mint without extensions, with maker and resolver ATAs both funded
+  const lifecycleMint = async () => {
+    const mint = await createMint2022([], () => []);
+    const { makerAccount, resolverAccount } = await fundedAccounts(mint);
+    await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+    return { mint, makerAccount, resolverAccount };
+  };
+
+  const secretFor = (label: string) => Buffer.from(label.padEnd(32, '\0'));
+
+  // Source hashlocks are reserved per maker, destination ones globally
+  const hashlockNullifierPda = (side: string, hashlock: number[] | Buffer, orderMaker = maker.publicKey) =>
+    PublicKey.findProgramAddressSync(
+      side === 'order_source'
+        ? [Buffer.from('nullifier'), Buffer.from(side), orderMaker.toBuffer(), Buffer.from(hashlock)]
+        : [Buffer.from('nullifier'), Buffer.from(side), Buffer.from(hashlock)],
+      program.programId
+    )[0];
+
+  const nullifierPdas = (
+    orderState: PublicKey,
+    side: 'order_source' | 'order_destination',
+    hashlock: number[],
+    orderMaker = maker.publicKey
+  ) => ({
+    orderNullifier: PublicKey.findProgramAddressSync([Buffer.from('nullifier'), orderState.toBuffer()], program.programId)[0],
+    hashlockNullifier: hashlockNullifierPda(side, hashlock, orderMaker),
+  });
+
+  const secretRecordPda = (hashlock: number[]) =>
+    PublicKey.findProgramAddressSync([Buffer.from('secret'), Buffer.from(hashlock)], program.programId)[0];
+
+  // Opens a single-fill source order of 1 token named after `label`, which also seeds its id and secret
+  const openSourceOrder = async (
+    label: string,
+    mint: PublicKey,
+    makerAccount: PublicKey,
+    orderTimelocks = quickTimelocks,
+    publicCancelRewardBps = 0
+  ) => {
+    const id = newOrderId(label);
+    const hashlock = Array.from(keccak_256(secretFor(label)));
+    const pdas = sourceOrderPdas(id);
+    await program.methods
+      .createOrderSource(id, resolver.publicKey, new BN(10 ** 6), hashlock, { keccak256: {} }, 1, orderTimelocks, safetyDepositSol, publicCancelRewardBps, flatAuction(), evmDestination(), null, null, false)
+      .accountsPartial({
+        orderStateSource: pdas.orderState,
+        maker: maker.publicKey,
+        makerTokenAccountSource: makerAccount,
+        escrowTokenAccountSource: pdas.escrowTokenAccount,
+        escrowAuthority: pdas.escrowAuthority,
+        tokenMintSource: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    return { id, hashlock, secret: secretFor(label), ...pdas };
+  };
+
+  type SourceOrder = Awaited<ReturnType<typeof openSourceOrder>>;
+
+  // Completes `order` in one fill, paying out to the resolver's ATA. The secret record is keyed by the hash of the
+  // secret under the order's algorithm, which only equals the hashlock when the secret is right.
+  const withdrawSourceOrder = (
+    order: SourceOrder,
+    mint: PublicKey,
+    resolverAccount: PublicKey,
+    caller: Keypair = resolver,
+    secretRecord = secretRecordPda(order.hashlock)
+  ) =>
+    program.methods
+      .withdrawSource(order.id, Array.from(order.secret), 0, [], new BN(10 ** 6))
+      .accountsPartial({
+        orderStateSource: order.orderState,
+        ...nullifierPdas(order.orderState, 'order_source', order.hashlock),
+        secretRecord,
+        caller: caller.publicKey,
+        resolver: resolver.publicKey,
+        rentPayer: maker.publicKey,
+        resolverTokenAccountSource: resolverAccount,
+        protocolFeeTokenAccount: null,
+        integratorFeeTokenAccount: null,
+        escrowTokenAccountSource: order.escrowTokenAccount,
+        escrowAuthority: order.escrowAuthority,
+        tokenMintSource: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([caller])
+      .rpc();
+
+  // Refunds `order`, which no resolver accepted, to the maker's ATA; the provider wallet cancels when no keypair is given
+  const cancelSourceOrder = (order: SourceOrder, mint: PublicKey, makerAccount: PublicKey, canceller?: Keypair) =>
+    program.methods
+      .cancelSource(order.id)
+      .accountsPartial({
+        orderStateSource: order.orderState,
+        ...nullifierPdas(order.orderState, 'order_source', order.hashlock),
+        maker: maker.publicKey,
+        rentPayer: maker.publicKey,
+        makerTokenAccountSource: makerAccount,
+        canceller: canceller ? canceller.publicKey : maker.publicKey,
+        config: configPda,
+        resolverStake: null,
+        treasury: null,
+        secretRecord: secretRecordPda(order.hashlock),
+        destinationHashlockNullifier: nullifierPdas(order.orderState, 'order_destination', order.hashlock).hashlockNullifier,
+        escrowTokenAccountSource: order.escrowTokenAccount,
+        escrowAuthority: order.escrowAuthority,
+        tokenMintSource: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers(canceller ? [canceller] : [])
+      .rpc();
+
+  // Fills an Ethereum order on Solana for 1 token on `orderTimelocks`, paying the maker in `mint`
+  const fillDestinationOrder = async (
+    label: string,
+    mint: PublicKey,
+    resolverAccount: PublicKey,
+    orderTimelocks = quickTimelocks,
+    publicCancelRewardBps = 0
+  ) => {
+    const hashlock = Array.from(keccak_256(secretFor(label)));
+    const source = evmSource();
+    const destination = solanaDestination(mint, orderTimelocks);
+    const id = orderIdFor(hashlock, flatAuction(), source, destination);
+    const pdas = destinationOrderPdas(id);
+    await program.methods
+      .fillOrderDestination(id, maker.publicKey, null, new BN(10 ** 6), hashlock, { keccak256: {} }, orderTimelocks, source, new BN(Math.floor(Date.now() / 1000) + 3_600), safetyDepositSol, publicCancelRewardBps, flatAuction(), destination, null, null)
+      .accountsPartial({
+        orderStateDestination: pdas.orderState,
+        sourceOrder: null,
+        resolver: resolver.publicKey,
+        resolverTokenAccountDestination: resolverAccount,
+        escrowTokenAccountDestination: pdas.escrowTokenAccount,
+        escrowAuthority: pdas.escrowAuthority,
+        tokenMintDestination: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([resolver])
+      .rpc();
+    return { id, hashlock, secret: secretFor(label), ...pdas };
+  };
+
+  type DestinationOrder = Awaited<ReturnType<typeof fillDestinationOrder>>;
+
+  // Pays `order` out to the maker's ATA; the provider wallet (the maker) calls when no keypair is given
+  const withdrawDestinationOrder = (order: DestinationOrder, mint: PublicKey, makerAccount: PublicKey, caller?: Keypair) =>
+    program.methods
+      .withdrawDestination(order.id, Array.from(order.secret))
+      .accountsPartial({
+        orderStateDestination: order.orderState,
+        ...nullifierPdas(order.orderState, 'order_destination', order.hashlock),
+        secretRecord: secretRecordPda(order.hashlock),
+        caller: caller ? caller.publicKey : maker.publicKey,
+        maker: maker.publicKey,
+        rentPayer: resolver.publicKey,
+        makerTokenAccountDestination: makerAccount,
+        protocolFeeTokenAccount: null,
+        integratorFeeTokenAccount: null,
+        escrowTokenAccountDestination: order.escrowTokenAccount,
+        escrowAuthority: order.escrowAuthority,
+        instructionsSysvar: null,
+        tokenMintDestination: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers(caller ? [caller] : [])
+      .rpc();
+
+  const tokenBalance = async (account: PublicKey) =>
+    (await getAccount(provider.connection, account, undefined, TOKEN_2022_PROGRAM_ID)).amount;
+
+  const expectError = async (tx: Promise<unknown>, error: string) => {
+    try {
+      await tx;
+      assert.fail(`expected ${error}`);
+    } catch (err) {
+      assert.include(String(err), error);
+    }
+  };
+
   it('Is initialized!', async () => {
     // Add your initialization tests here if needed
     // For now, we assume the program is deployed and ready.
     assert.ok(program.programId);
   });
 
+  it('Stakes, unstakes after the cooldown and withdraws resolver stake', async () => {
+    const [resolverStake] = PublicKey.findProgramAddressSync(
+      [Buffer.from('stake'), resolver.publicKey.toBuffer()],
+      program.programId
+    );
+    const stakeAmount = new BN(LAMPORTS_PER_SOL / 2);
+
+    await program.methods.depositStake(stakeAmount).accountsPartial({ resolver: resolver.publicKey }).signers([resolver]).rpc();
+    let stake = await program.account.resolverStake.fetch(resolverStake);
+    assert.ok(stake.stakedAmount.eq(stakeAmount));
+
+    const unstakeAmount = new BN(LAMPORTS_PER_SOL / 10);
+    await program.methods
+      .requestUnstake(unstakeAmount)
+      .accountsPartial({ resolver: resolver.publicKey })
+      .signers([resolver])
+      .rpc();
+    try {
+      await program.methods.withdrawStake().accountsPartial({ resolver: resolver.publicKey }).signers([resolver]).rpc();
+      assert.fail('withdrawal must wait for the cooldown');
+    } catch (err) {
+      assert.include(String(err), 'UnstakeCooldownActive');
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await program.methods.withdrawStake().accountsPartial({ resolver: resolver.publicKey }).signers([resolver]).rpc();
+    stake = await program.account.resolverStake.fetch(resolverStake);
+    assert.ok(stake.stakedAmount.eq(stakeAmount.sub(unstakeAmount)));
+    assert.ok(stake.pendingUnstakeAmount.isZero());
+  });
+
+  it('Pauses new orders and hands over the admin role', async () => {
+    const [config] = PublicKey.findProgramAddressSync([Buffer.from('config')], program.programId);
+    const newAdmin = Keypair.generate();
+
+    await program.methods.setPaused(true).accountsPartial({ config, admin: maker.publicKey }).rpc();
+    assert.isTrue((await program.account.protocolConfig.fetch(config)).paused);
+    await program.methods.setPaused(false).accountsPartial({ config, admin: maker.publicKey }).rpc();
+
+    const proposed = new Promise<any>((resolve) => {
+      const listener = program.addEventListener('adminTransferProposed', (event) => {
+        program.removeEventListener(listener);
+        resolve(event);
+      });
+    });
+    await program.methods.transferAdmin(newAdmin.publicKey).accountsPartial({ config, admin: maker.publicKey }).rpc();
+    const proposal = await proposed;
+    assert.ok(proposal.admin.equals(maker.publicKey));
+    assert.ok(proposal.pendingAdmin.equals(newAdmin.publicKey), 'indexers see the pending admin before it accepts');
+    await program.methods.acceptAdmin().accountsPartial({ config, newAdmin: newAdmin.publicKey }).signers([newAdmin]).rpc();
+    assert.ok((await program.account.protocolConfig.fetch(config)).admin.equals(newAdmin.publicKey));
+
+    // Hand the role back so later tests can keep using the provider wallet
+    await program.methods
+      .transferAdmin(maker.publicKey)
+      .accountsPartial({ config, admin: newAdmin.publicKey })
+      .signers([newAdmin])
+      .rpc();
+    await program.methods.acceptAdmin().accountsPartial({ config, newAdmin: maker.publicKey }).rpc();
+  });
+
+  it('Caps the protocol fee so integrator fees always fit', async () => {
+    const [config] = PublicKey.findProgramAddressSync([Buffer.from('config')], program.programId);
+
+    // maxIntegratorFeeBps is 100, so anything above 9_900 would let fees exceed the settled amount
+    try {
+      await program.methods.setProtocolFee(9_901).accountsPartial({ config, admin: maker.publicKey }).rpc();
+      assert.fail('protocol fee above the cap was accepted');
+    } catch (err) {
+      assert.include(String(err), 'InvalidBasisPoints');
+    }
+
+    await program.methods.setProtocolFee(30).accountsPartial({ config, admin: maker.publicKey }).rpc();
+    assert.equal((await program.account.protocolConfig.fetch(config)).protocolFeeBps, 30);
+    // Back to zero so the token tests below can assert exact balances
+    await program.methods.setProtocolFee(0).accountsPartial({ config, admin: maker.publicKey }).rpc();
+
+    // Relayers finalizing destination withdrawals can be paid up to the whole safety deposit
+    try {
+      await program.methods.setRelayerReward(10_001).accountsPartial({ config, admin: maker.publicKey }).rpc();
+      assert.fail('relayer reward above 100% was accepted');
+    } catch (err) {
+      assert.include(String(err), 'InvalidBasisPoints');
+    }
+    assert.equal((await program.account.protocolConfig.fetch(config)).relayerRewardBps, 2_500);
+  });
+
   // --- Source Chain Logic Tests ---
 
   it('Creates an order on the source chain', async () => {
@@ -193,4 +718,1942 @@ describe('fusion_escrow_solana', () => {
     console.log("TODO: Test cancel_destination");
   });
 
+  describe('Order ids', () => {
+    it('Only accepts a bound order id that hashes the order parameters', async () => {
+      const mint = await createMint2022([], () => []);
+      const { makerAccount } = await fundedAccounts(mint);
+      const orderSecret = Buffer.from('boundOrderSecret'.padEnd(32, '\0'));
+      const hashSecret = Buffer.from(keccak_256(orderSecret));
+      const auction = flatAuction();
+
+      const createOrder = (id: number[]) => {
+        const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id);
+        return program.methods
+          .createOrderSource(
+            id,
+            resolver.publicKey,
+            new BN(10 ** 6),
+            Array.from(hashSecret),
+            { keccak256: {} },
+            1,
+            timelocks,
+            safetyDepositSol,
+            0,
+            auction,
+            evmDestination(),
+            null, // No donation metadata
+            null,
+            true // Bind the order id
+          )
+          .accountsPartial({
+            orderStateSource: orderState,
+            maker: maker.publicKey,
+            makerTokenAccountSource: makerAccount,
+            escrowTokenAccountSource: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintSource: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      };
+
+      try {
+        await createOrder(newOrderId('squattedOrderId'));
+        assert.fail('unbound order id was accepted');
+      } catch (err) {
+        assert.include(String(err), 'OrderIdMismatch');
+      }
+
+      // The source leg of a Solana order is its mint, the amount escrowed and its schedule
+      const source = { ...evmSource(501), token: mint.toBuffer() };
+      const boundId = orderIdFor(hashSecret, auction, source, evmDestination());
+      await createOrder(boundId);
+      const order = await program.account.orderStateSource.fetch(sourceOrderPdas(boundId).orderState);
+      assert.isTrue(order.orderIdBound);
+    });
+
+    it('Forces fills of orders from other chains to carry an order id bound to every term', async () => {
+      const mint = await createMint2022([], () => []);
+      const { resolverAccount } = await fundedAccounts(mint);
+      await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+      const hashSecret = Array.from(keccak_256(Buffer.from('forcedBindingSecret')));
+      const source = evmSource();
+
+      const fill = (id: number[], destination = solanaDestination(mint)) => {
+        const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(id);
+        return program.methods
+          .fillOrderDestination(id, maker.publicKey, null, new BN(10 ** 6), hashSecret, { keccak256: {} }, timelocks, source, new BN(Math.floor(Date.now() / 1000) + 3_600), safetyDepositSol, 0, flatAuction(), destination, null, null)
+          .accountsPartial({
+            orderStateDestination: orderState,
+            sourceOrder: null,
+            resolver: resolver.publicKey,
+            resolverTokenAccountDestination: resolverAccount,
+            escrowTokenAccountDestination: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([resolver])
+          .rpc();
+      };
+      const expectRejected = async (tx: Promise<unknown>, error: RegExp) => {
+        try {
+          await tx;
+          assert.fail('fill with unproven terms was accepted');
+        } catch (err) {
+          assert.match(String(err), error);
+        }
+      };
+
+      // The filler cannot opt out of the binding any more
+      await expectRejected(fill(newOrderId('unboundCrossChainFill')), /OrderIdMismatch/);
+      // An id the maker signed over a different source amount does not cover this fill
+      await expectRejected(fill(orderIdFor(hashSecret, flatAuction(), evmSource(1, new BN(2 * 10 ** 6)), solanaDestination(mint))), /OrderIdMismatch/);
+      // Nor can the filler bind an id to a payout the fill does not make
+      const elsewhere = { ...solanaDestination(mint), recipient: resolver.publicKey.toBuffer() };
+      await expectRejected(fill(orderIdFor(hashSecret, flatAuction(), source, elsewhere), elsewhere), /DestinationDescriptorMismatch/);
+
+      const boundId = orderIdFor(hashSecret, flatAuction(), source, solanaDestination(mint));
+      await fill(boundId);
+      const order = await program.account.orderStateDestination.fetch(destinationOrderPdas(boundId).orderState);
+      assert.isTrue(order.orderIdBound);
+    });
+  });
+
+  describe('Dutch auction', () => {
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+    const now = () => Math.floor(Date.now() / 1000);
+    // 3 tokens at the start, falling linearly to 1 token over an hour
+    const curve = (startTimestamp: number) => ({
+      startAmount: new BN(3 * 10 ** 6),
+      endAmount: new BN(10 ** 6),
+      startTimestamp: new BN(startTimestamp),
+      duration: 3_600,
+      points: [],
+    });
+    const expectRejected = async (tx: Promise<unknown>, error: RegExp) => {
+      try {
+        await tx;
+        assert.fail('fill off the auction curve was accepted');
+      } catch (err) {
+        assert.match(String(err), error);
+      }
+    };
+
+    const fill = (
+      label: string,
+      auction: ReturnType<typeof flatAuction>,
+      amount: BN,
+      { id, source = evmSource(), sourceOrder = null, destination = solanaDestination(mint), sourceCancellation = new BN(now() + 3_600) }: {
+        id?: number[];
+        source?: ReturnType<typeof evmSource>;
+        sourceOrder?: PublicKey | null;
+        destination?: ReturnType<typeof solanaDestination>;
+        sourceCancellation?: BN;
+      } = {}
+    ) => {
+      const hashSecret = Array.from(keccak_256(Buffer.from(label)));
+      const orderId = id ?? orderIdFor(hashSecret, auction, source, destination);
+      const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(orderId);
+      return program.methods
+        .fillOrderDestination(orderId, maker.publicKey, null, amount, hashSecret, { keccak256: {} }, destination.timelocks, source, sourceCancellation, safetyDepositSol, 0, auction, destination, null, null)
+        .accountsPartial({
+          orderStateDestination: orderState,
+          sourceOrder,
+          resolver: resolver.publicKey,
+          resolverTokenAccountDestination: resolverAccount,
+          escrowTokenAccountDestination: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintDestination: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+    };
+
+    before(async () => {
+      mint = await createMint2022([], () => []);
+      ({ makerAccount, resolverAccount } = await fundedAccounts(mint));
+      await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+    });
+
+    it('rejects fills below the curve at every point of the auction', async () => {
+      const amount = new BN(1.5 * 10 ** 6);
+      // Before the start (3 tokens), at the start (3 tokens) and halfway through (about 2 tokens)
+      for (const [label, startTimestamp] of [
+        ['auctionNotStarted', now() + 600],
+        ['auctionStarted', now()],
+        ['auctionHalfway', now() - 1_800],
+      ] as [string, number][]) {
+        await expectRejected(fill(label, curve(startTimestamp), amount), /AmountBelowAuctionCurve/);
+      }
+
+      // Three quarters in the curve is down to about 1.5 tokens, and after the end 1 token is enough
+      await fill('auctionThreeQuarters', curve(now() - 2_760), amount);
+      await fill('auctionEnded', curve(now() - 4_000), new BN(10 ** 6));
+    });
+
+    it('rejects fills that swap in a cheaper curve than the maker committed to', async () => {
+      const makerCurve = curve(now());
+      const cheaperCurve = curve(now() - 4_000);
+      const amount = new BN(10 ** 6);
+
+      // Order from another chain: its bound id covers the maker's curve, not the one the resolver passes
+      const label = 'cheaperCurveCrossChain';
+      const boundId = orderIdFor(Array.from(keccak_256(Buffer.from(label))), makerCurve, evmSource(), solanaDestination(mint));
+      await expectRejected(fill(label, cheaperCurve, amount, { id: boundId }), /OrderIdMismatch/);
+
+      // Solana order: the fill is checked against the curve stored in the source order
+      const sameChainLabel = 'cheaperCurveSameChain';
+      const hashSecret = Array.from(keccak_256(Buffer.from(sameChainLabel)));
+      const id = newOrderId(sameChainLabel);
+      const sourceOrder = sourceOrderPdas(id);
+      const destinationTimelocks = { withdrawal: 1, publicWithdrawal: 100, cancellation: 300, publicCancellation: 400 };
+      const destination = solanaDestination(mint, destinationTimelocks);
+      await program.methods
+        .createOrderSource(id, resolver.publicKey, amount, hashSecret, { keccak256: {} }, 1, timelocks, safetyDepositSol, 0, makerCurve, destination, null, null, false)
+        .accountsPartial({
+          orderStateSource: sourceOrder.orderState,
+          maker: maker.publicKey,
+          makerTokenAccountSource: makerAccount,
+          escrowTokenAccountSource: sourceOrder.escrowTokenAccount,
+          escrowAuthority: sourceOrder.escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      const created = await program.account.orderStateSource.fetch(sourceOrder.orderState);
+      await expectRejected(
+        fill(sameChainLabel, cheaperCurve, amount, {
+          id,
+          source: { chainId: new BN(501), escrow: sourceOrder.orderState.toBuffer(), token: mint.toBuffer(), amount, timelocks },
+          sourceOrder: sourceOrder.orderState,
+          destination,
+          sourceCancellation: created.creationTimestamp.addn(timelocks.cancellation),
+        }),
+        /SourceOrderMismatch/
+      );
+    });
+  });
+
+  describe('Hashlock reservation', () => {
+    it('rejects a second live order that reuses an open hashlock', async () => {
+      const mint = await createMint2022([], () => []);
+      const { makerAccount, resolverAccount } = await fundedAccounts(mint);
+      await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+      const hashSecret = Array.from(keccak_256(Buffer.from('sharedHashlockSecret'.padEnd(32, '\0'))));
+
+      const createSource = (id: number[]) => {
+        const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id);
+        return program.methods
+          .createOrderSource(id, resolver.publicKey, new BN(10 ** 6), hashSecret, { keccak256: {} }, 1, timelocks, safetyDepositSol, 0, flatAuction(), evmDestination(), null, null, false)
+          .accountsPartial({
+            orderStateSource: orderState,
+            maker: maker.publicKey,
+            makerTokenAccountSource: makerAccount,
+            escrowTokenAccountSource: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintSource: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      };
+      // Orders from other chains carry bound ids, so each fill mirrors a different source amount
+      const fillDestination = (sourceAmount: number) => {
+        const source = evmSource(1, new BN(sourceAmount));
+        const id = orderIdFor(hashSecret, flatAuction(), source, solanaDestination(mint));
+        const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(id);
+        return program.methods
+          .fillOrderDestination(id, maker.publicKey, null, new BN(10 ** 6), hashSecret, { keccak256: {} }, timelocks, source, new BN(Math.floor(Date.now() / 1000) + 3_600), safetyDepositSol, 0, flatAuction(), solanaDestination(mint), null, null)
+          .accountsPartial({
+            orderStateDestination: orderState,
+            sourceOrder: null,
+            resolver: resolver.publicKey,
+            resolverTokenAccountDestination: resolverAccount,
+            escrowTokenAccountDestination: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([resolver])
+          .rpc();
+      };
+      const expectReserved = async (tx: Promise<unknown>) => {
+        try {
+          await tx;
+          assert.fail('order reusing a reserved hashlock was accepted');
+        } catch (err) {
+          assert.match(String(err), /already in use|custom program error: 0x0/);
+        }
+      };
+
+      const firstId = newOrderId('sharedHashlockFirst');
+      await createSource(firstId);
+      const sourceReservation = hashlockNullifierPda('order_source', hashSecret);
+      const reservation = await program.account.nullifier.fetch(sourceReservation);
+      assert.ok(reservation.order.equals(sourceOrderPdas(firstId).orderState));
+      assert.deepEqual(reservation.outcome, { reserved: {} });
+
+      // A copy with the same hashlock would otherwise be stranded once the first order's tombstone is written
+      await expectReserved(createSource(newOrderId('sharedHashlockSecond')));
+
+      // The destination side keeps its own reservation, so the matching fill goes through once
+      await fillDestination(10 ** 6);
+      await expectReserved(fillDestination(2 * 10 ** 6));
+    });
+
+    it('does not let another maker block a hashlock it has seen', async () => {
+      const mint = await createMint2022([], () => []);
+      const { makerAccount } = await fundedAccounts(mint);
+      const squatterAccount = await createAccount(provider.connection, maker.payer, mint, anotherUser.publicKey, undefined, undefined, TOKEN_2022_PROGRAM_ID);
+      await mintTo(provider.connection, maker.payer, mint, squatterAccount, maker.publicKey, 1, [], undefined, TOKEN_2022_PROGRAM_ID);
+      // e.g. read from a broadcast intent or from the other chain's leg
+      const hashSecret = Array.from(keccak_256(Buffer.from('squattedHashlockSecret'.padEnd(32, '\0'))));
+
+      const createSource = (id: number[], owner: Keypair | null, ownerAccount: PublicKey, amount: number) => {
+        const orderMaker = owner ? owner.publicKey : maker.publicKey;
+        const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id, orderMaker);
+        return program.methods
+          .createOrderSource(id, resolver.publicKey, new BN(amount), hashSecret, { keccak256: {} }, 1, timelocks, safetyDepositSol, 0, flatAuction(), evmDestination(), null, null, false)
+          .accountsPartial({
+            orderStateSource: orderState,
+            ...nullifierPdas(orderState, 'order_source', hashSecret, orderMaker),
+            maker: orderMaker,
+            makerTokenAccountSource: ownerAccount,
+            escrowTokenAccountSource: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintSource: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers(owner ? [owner] : [])
+          .rpc();
+      };
+
+      // A dust order reserves the hashlock for its own maker only
+      await createSource(newOrderId('squattedHashlockSquatter'), anotherUser, squatterAccount, 1);
+      const makerId = newOrderId('squattedHashlockMaker');
+      await createSource(makerId, null, makerAccount, 10 ** 6);
+      const reservation = await program.account.nullifier.fetch(hashlockNullifierPda('order_source', hashSecret));
+      assert.ok(reservation.order.equals(sourceOrderPdas(makerId).orderState));
+    });
+  });
+
+  describe('Resolver slashing', () => {
+    const [resolverStake] = PublicKey.findProgramAddressSync(
+      [Buffer.from('stake'), resolver.publicKey.toBuffer()],
+      program.programId
+    );
+    const slashAmount = new BN(LAMPORTS_PER_SOL / 20);
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+
+    useQuickTimeouts();
+
+    // Creates an order on the quick schedule and has the resolver accept it
+    const createAccepted = async (
+      label: string,
+      destination: ReturnType<typeof evmDestination>,
+      hashlock = Buffer.from(keccak_256(secretFor(label))),
+      partsAmount = 1
+    ) => {
+      const id = newOrderId(label);
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id);
+      await program.methods
+        .createOrderSource(id, resolver.publicKey, new BN(10 ** 6), Array.from(hashlock), { keccak256: {} }, partsAmount, quickTimelocks, safetyDepositSol, 0, flatAuction(), destination, null, null, false)
+        .accountsPartial({
+          orderStateSource: orderState,
+          maker: maker.publicKey,
+          makerTokenAccountSource: makerAccount,
+          escrowTokenAccountSource: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      await program.methods
+        .acceptOrderSource(id)
+        .accountsPartial({ orderStateSource: orderState, resolver: resolver.publicKey, resolverStake, config: configPda })
+        .signers([resolver])
+        .rpc();
+      return id;
+    };
+
+    // The maker cancels once the exclusive cancellation window opens; returns how much stake was slashed
+    const cancelAfterTimeout = async (id: number[], label: string, hashlock = Buffer.from(keccak_256(secretFor(label)))) => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id);
+      const stakeBefore = (await program.account.resolverStake.fetch(resolverStake)).stakedAmount;
+      await sleep(7000);
+      await program.methods
+        .cancelSource(id)
+        .accountsPartial({
+          orderStateSource: orderState,
+          orderNullifier: PublicKey.findProgramAddressSync([Buffer.from('nullifier'), orderState.toBuffer()], program.programId)[0],
+          hashlockNullifier: hashlockNullifierPda('order_source', hashlock),
+          maker: maker.publicKey,
+          rentPayer: maker.publicKey,
+          makerTokenAccountSource: makerAccount,
+          canceller: maker.publicKey,
+          config: configPda,
+          resolverStake,
+          treasury: maker.publicKey,
+          secretRecord: PublicKey.findProgramAddressSync([Buffer.from('secret'), hashlock], program.programId)[0],
+          destinationHashlockNullifier: PublicKey.findProgramAddressSync(
+            [Buffer.from('nullifier'), Buffer.from('order_destination'), hashlock],
+            program.programId
+          )[0],
+          escrowTokenAccountSource: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      return stakeBefore.sub((await program.account.resolverStake.fetch(resolverStake)).stakedAmount);
+    };
+
+    before(async () => {
+      mint = await createMint2022([], () => []);
+      ({ makerAccount } = await fundedAccounts(mint));
+      await program.methods
+        .depositStake(new BN(LAMPORTS_PER_SOL / 5))
+        .accountsPartial({ resolver: resolver.publicKey })
+        .signers([resolver])
+        .rpc();
+    });
+
+    it('does not slash a resolver whose maker withholds the secret', async () => {
+      const label = 'slashWithheldSecret';
+      const id = await createAccepted(label, evmDestination());
+      assert.ok((await cancelAfterTimeout(id, label)).isZero(), 'cancelling without evidence of fault must not slash');
+    });
+
+    it('slashes a resolver that left a public secret unused', async () => {
+      const label = 'slashUnusedSecret';
+      const id = await createAccepted(label, evmDestination());
+      // The maker revealed the secret on the destination chain and publishes it here, well before public withdrawal
+      await program.methods
+        .publishSecret({ keccak256: {} }, Array.from(secretFor(label)))
+        .accountsPartial({
+          secretRecord: PublicKey.findProgramAddressSync([Buffer.from('secret'), Buffer.from(keccak_256(secretFor(label)))], program.programId)[0],
+          publisher: maker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.ok((await cancelAfterTimeout(id, label)).eq(slashAmount));
+    });
+
+    it('slashes a resolver that never filled a Solana destination', async () => {
+      const label = 'slashUnfilledDestination';
+      const id = await createAccepted(label, solanaDestination(mint));
+      assert.ok((await cancelAfterTimeout(id, label)).eq(slashAmount));
+    });
+
+    it('slashes a resolver that left a reported part of a partial-fill order unused', async () => {
+      const label = 'slashUnusedPart';
+      // Two parts, three secrets; leaves are keccak256(uint64 index || secret hash) and nodes hash sorted pairs
+      const secrets = [0, 1, 2].map((index) => secretFor(`${label}${index}`));
+      const secretHashes = secrets.map((secret) => Buffer.from(keccak_256(secret)));
+      const leaves = secretHashes.map((hash, index) => {
+        const indexBytes = Buffer.alloc(8);
+        indexBytes.writeBigUInt64BE(BigInt(index));
+        return Buffer.from(keccak_256(Buffer.concat([indexBytes, hash])));
+      });
+      const parent = (a: Buffer, b: Buffer) => Buffer.from(keccak_256(Buffer.concat(Buffer.compare(a, b) <= 0 ? [a, b] : [b, a])));
+      const root = parent(parent(leaves[0], leaves[1]), leaves[2]);
+      const id = await createAccepted(label, evmDestination(), root, 2);
+
+      // The maker revealed secret 1 on the destination chain after the resolver filled half there
+      const secretRecord = PublicKey.findProgramAddressSync([Buffer.from('secret'), secretHashes[1]], program.programId)[0];
+      await program.methods
+        .publishSecret({ keccak256: {} }, Array.from(secrets[1]))
+        .accountsPartial({ secretRecord, publisher: maker.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+      const { orderState } = sourceOrderPdas(id);
+      const report = (index: number, proof: Buffer[]) =>
+        program.methods
+          .reportRevealedSecret(id, index, proof.map((node) => Array.from(node)))
+          .accountsPartial({ orderStateSource: orderState, secretRecord })
+          .rpc();
+      await expectError(report(0, [leaves[1], leaves[2]]), 'InvalidMerkleProof');
+      await report(1, [leaves[0], leaves[2]]);
+      assert.equal((await program.account.orderStateSource.fetch(orderState)).revealedPart, 1);
+
+      assert.ok((await cancelAfterTimeout(id, label, root)).eq(slashAmount));
+    });
+
+    it('keeps stake in the vault until accepted orders can be publicly cancelled', async () => {
+      const id = await createAccepted('slashUnstakeLock', evmDestination());
+      const { orderState } = sourceOrderPdas(id);
+      const order = await program.account.orderStateSource.fetch(orderState);
+      const stake = await program.account.resolverStake.fetch(resolverStake);
+      assert.equal(stake.lockedUntil.toNumber(), order.creationTimestamp.toNumber() + quickTimelocks.publicCancellation);
+
+      // The 2s unstake cooldown is long over, but the order still has its whole schedule ahead
+      await program.methods.requestUnstake(new BN(1)).accountsPartial({ resolver: resolver.publicKey }).signers([resolver]).rpc();
+      await sleep(2500);
+      await expectError(
+        program.methods.withdrawStake().accountsPartial({ resolver: resolver.publicKey }).signers([resolver]).rpc(),
+        'StakeLocked'
+      );
+    });
+  });
+
+  describe('EVM makers', () => {
+    it('withdraws only with a signature naming this order and its resolver', async () => {
+      const mint = await createMint2022([], () => []);
+      const { makerAccount, resolverAccount } = await fundedAccounts(mint);
+      await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+      // The maker only holds an Ethereum key and is paid out to the provider wallet on Solana
+      const evmKey = secp256k1.utils.randomPrivateKey();
+      const makerEvmAddress = Array.from(Secp256k1Program.publicKeyToEthAddress(secp256k1.getPublicKey(evmKey, false).slice(1)));
+      const orderSecret = Buffer.from('evmMakerSecret'.padEnd(32, '\0'));
+      const hashSecret = Array.from(keccak_256(orderSecret));
+      const source = evmSource();
+      const id = orderIdFor(hashSecret, flatAuction(), source, solanaDestination(mint));
+      const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(id);
+
+      await program.methods
+        .fillOrderDestination(id, maker.publicKey, makerEvmAddress, new BN(10 ** 6), hashSecret, { keccak256: {} }, timelocks, source, new BN(Math.floor(Date.now() / 1000) + 3_600), safetyDepositSol, 0, flatAuction(), solanaDestination(mint), null, null)
+        .accountsPartial({
+          orderStateDestination: orderState,
+          sourceOrder: null,
+          resolver: resolver.publicKey,
+          resolverTokenAccountDestination: resolverAccount,
+          escrowTokenAccountDestination: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintDestination: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+
+      // EIP-191 message over the program, the order account and id, the payout recipient and the resolver
+      const signedWithdrawal = (orderResolver: PublicKey) => {
+        const payload = Buffer.concat([
+          Buffer.from('fusion_escrow_solana:evm_withdrawal'),
+          program.programId.toBuffer(),
+          orderState.toBuffer(),
+          Buffer.from(id),
+          maker.publicKey.toBuffer(),
+          orderResolver.toBuffer(),
+        ]);
+        const message = Buffer.concat([Buffer.from(`\x19Ethereum Signed Message:\n${payload.length}`), payload]);
+        return Secp256k1Program.createInstructionWithPrivateKey({ privateKey: evmKey, message });
+      };
+      const withdraw = (signature: TransactionInstruction) =>
+        program.methods
+          .withdrawDestination(id, Array.from(orderSecret))
+          .accountsPartial({
+            orderStateDestination: orderState,
+            orderNullifier: PublicKey.findProgramAddressSync([Buffer.from('nullifier'), orderState.toBuffer()], program.programId)[0],
+            hashlockNullifier: PublicKey.findProgramAddressSync(
+              [Buffer.from('nullifier'), Buffer.from('order_destination'), Buffer.from(hashSecret)],
+              program.programId
+            )[0],
+            secretRecord: PublicKey.findProgramAddressSync([Buffer.from('secret'), Buffer.from(hashSecret)], program.programId)[0],
+            caller: maker.publicKey, // Any submitter of the maker's signature
+            maker: maker.publicKey,
+            rentPayer: resolver.publicKey,
+            makerTokenAccountDestination: makerAccount,
+            protocolFeeTokenAccount: null,
+            integratorFeeTokenAccount: null,
+            escrowTokenAccountDestination: escrowTokenAccount,
+            escrowAuthority,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .preInstructions([signature])
+          .rpc();
+
+      await sleep(2000); // Past the finality lock
+      try {
+        await withdraw(signedWithdrawal(anotherUser.publicKey));
+        assert.fail('signature for another resolver was accepted');
+      } catch (err) {
+        assert.include(String(err), 'InvalidSignatureInstruction');
+      }
+
+      const makerBefore = await getAccount(provider.connection, makerAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      await withdraw(signedWithdrawal(resolver.publicKey));
+      const makerAfter = await getAccount(provider.connection, makerAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      assert.equal((makerAfter.amount - makerBefore.amount).toString(), (10 ** 6).toString());
+    });
+  });
+
+  describe('Order stages', () => {
+    // Finality lock until 3s, exclusive withdrawal until 5s, exclusive cancellation from 7s
+    const stagedTimelocks = { withdrawal: 3, publicWithdrawal: 5, cancellation: 7, publicCancellation: 60 };
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+
+    useQuickTimeouts();
+
+    before(async () => {
+      ({ mint, makerAccount, resolverAccount } = await lifecycleMint());
+    });
+
+    it('holds withdrawals through the finality lock, then lets the resolver withdraw', async () => {
+      const order = await openSourceOrder('stagesFinalityLock', mint, makerAccount, stagedTimelocks);
+      await expectError(withdrawSourceOrder(order, mint, resolverAccount), 'FinalityLockActive');
+      await expectError(cancelSourceOrder(order, mint, makerAccount), 'TimeoutNotExpired');
+
+      await sleep(3500);
+      const resolverBefore = await getAccount(provider.connection, resolverAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      await withdrawSourceOrder(order, mint, resolverAccount);
+      const resolverAfter = await getAccount(provider.connection, resolverAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      assert.equal((resolverAfter.amount - resolverBefore.amount).toString(), (10 ** 6).toString());
+    });
+
+    it('ends withdrawals at cancellation, which only the parties may use at first', async () => {
+      const order = await openSourceOrder('stagesCancellation', mint, makerAccount, stagedTimelocks);
+      await sleep(7500);
+      await expectError(withdrawSourceOrder(order, mint, resolverAccount), 'TimeoutExpired');
+      await expectError(cancelSourceOrder(order, mint, makerAccount, anotherUser), 'StageRestricted');
+
+      const makerBefore = await getAccount(provider.connection, makerAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      await cancelSourceOrder(order, mint, makerAccount);
+      const makerAfter = await getAccount(provider.connection, makerAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      assert.equal((makerAfter.amount - makerBefore.amount).toString(), (10 ** 6).toString());
+    });
+  });
+
+  describe('Public withdrawal', () => {
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+
+    useQuickTimeouts();
+
+    before(async () => {
+      ({ mint, makerAccount, resolverAccount } = await lifecycleMint());
+    });
+
+    it('lets anyone with the secret finish a source order for the resolver and earn its deposit', async () => {
+      const order = await openSourceOrder('publicWithdrawSource', mint, makerAccount);
+      await sleep(1500); // Exclusive withdrawal
+      await expectError(withdrawSourceOrder(order, mint, resolverAccount, anotherUser), 'CallerNotResolver');
+
+      await sleep(3000); // Public withdrawal
+      const resolverBefore = await tokenBalance(resolverAccount);
+      const callerBefore = await provider.connection.getBalance(anotherUser.publicKey);
+      await withdrawSourceOrder(order, mint, resolverAccount, anotherUser);
+
+      assert.equal((await tokenBalance(resolverAccount)) - resolverBefore, BigInt(10 ** 6), 'tokens still go to the resolver');
+      // The caller paid for the tombstone and the secret record out of the deposit it earned
+      const { orderNullifier } = nullifierPdas(order.orderState, 'order_source', order.hashlock);
+      const rentPaid =
+        (await provider.connection.getBalance(orderNullifier)) + (await provider.connection.getBalance(secretRecordPda(order.hashlock)));
+      const callerAfter = await provider.connection.getBalance(anotherUser.publicKey);
+      assert.equal(callerAfter - callerBefore, safetyDepositSol.toNumber() - rentPaid);
+    });
+
+    it('lets anyone with the secret finish a destination order for the maker', async () => {
+      const order = await fillDestinationOrder('publicWithdrawDestination', mint, resolverAccount);
+      await sleep(1500);
+      await expectError(withdrawDestinationOrder(order, mint, makerAccount, anotherUser), 'CallerNotMaker');
+
+      await sleep(3000);
+      const makerBefore = await tokenBalance(makerAccount);
+      await withdrawDestinationOrder(order, mint, makerAccount, anotherUser);
+      assert.equal((await tokenBalance(makerAccount)) - makerBefore, BigInt(10 ** 6), 'tokens still go to the maker');
+    });
+  });
+
+  describe('Keeper rewards', () => {
+    // Exclusive cancellation from 3s, public cancellation from 5s
+    const keeperTimelocks = { withdrawal: 1, publicWithdrawal: 2, cancellation: 3, publicCancellation: 5 };
+    const rewardBps = 5_000;
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+
+    useQuickTimeouts();
+
+    before(async () => {
+      ({ mint, makerAccount, resolverAccount } = await lifecycleMint());
+    });
+
+    // What a keeper nets: its share of the deposit minus the rent of the order tombstone it creates
+    const expectKeeperPaid = async (orderState: PublicKey, keeperBefore: number) => {
+      const tombstoneRent = await provider.connection.getBalance(
+        PublicKey.findProgramAddressSync([Buffer.from('nullifier'), orderState.toBuffer()], program.programId)[0]
+      );
+      const keeperAfter = await provider.connection.getBalance(anotherUser.publicKey);
+      assert.equal(keeperAfter - keeperBefore, (safetyDepositSol.toNumber() * rewardBps) / 10_000 - tombstoneRent);
+    };
+
+    it('pays a keeper that refunds an expired source order during public cancellation', async () => {
+      const order = await openSourceOrder('keeperSource', mint, makerAccount, keeperTimelocks, rewardBps);
+      await sleep(3500);
+      await expectError(cancelSourceOrder(order, mint, makerAccount, anotherUser), 'StageRestricted');
+
+      await sleep(2000);
+      const makerBefore = await tokenBalance(makerAccount);
+      const keeperBefore = await provider.connection.getBalance(anotherUser.publicKey);
+      await cancelSourceOrder(order, mint, makerAccount, anotherUser);
+      assert.equal(((await tokenBalance(makerAccount)) - makerBefore).toString(), (10 ** 6).toString(), 'refund still goes to the maker');
+      await expectKeeperPaid(order.orderState, keeperBefore);
+    });
+
+    it('pays a keeper that refunds an expired destination order during public cancellation', async () => {
+      const order = await fillDestinationOrder('keeperDestination', mint, resolverAccount, keeperTimelocks, rewardBps);
+      const cancelDestination = () =>
+        program.methods
+          .cancelDestination(order.id)
+          .accountsPartial({
+            orderStateDestination: order.orderState,
+            ...nullifierPdas(order.orderState, 'order_destination', order.hashlock),
+            rentPayer: resolver.publicKey,
+            resolver: resolver.publicKey,
+            resolverTokenAccountDestination: resolverAccount,
+            canceller: anotherUser.publicKey,
+            escrowTokenAccountDestination: order.escrowTokenAccount,
+            escrowAuthority: order.escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([anotherUser])
+          .rpc();
+
+      await sleep(3500);
+      await expectError(cancelDestination(), 'CallerNotResolver');
+
+      await sleep(2000);
+      const resolverBefore = await tokenBalance(resolverAccount);
+      const keeperBefore = await provider.connection.getBalance(anotherUser.publicKey);
+      await cancelDestination();
+      assert.equal(((await tokenBalance(resolverAccount)) - resolverBefore).toString(), (10 ** 6).toString(), 'refund still goes to the resolver');
+      await expectKeeperPaid(order.orderState, keeperBefore);
+    });
+  });
+
+  describe('Order intents', () => {
+    const gaslessMaker = Keypair.generate(); // Holds tokens but no SOL
+    let mint: PublicKey;
+    let gaslessMakerAccount: PublicKey;
+
+    before(async () => {
+      mint = await createMint2022([], () => []);
+      gaslessMakerAccount = await createAccount(
+        provider.connection,
+        maker.payer,
+        mint,
+        gaslessMaker.publicKey,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+      await mintTo(provider.connection, maker.payer, mint, gaslessMakerAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+    });
+
+    const intentFor = (label: string, nonce: number) => ({
+      maker: gaslessMaker.publicKey,
+      terms: {
+        orderId: newOrderId(label),
+        orderIdBound: false,
+        resolver: resolver.publicKey,
+        tokenMintSource: mint,
+        amount: new BN(10 ** 6),
+        hashSecret: Array.from(keccak_256(secretFor(label))),
+        hashAlgorithm: { keccak256: {} },
+        partsAmount: 1,
+        timelocks,
+        safetyDepositSol,
+        publicCancelRewardBps: 0,
+        auction: flatAuction(),
+        destination: evmDestination(),
+        metadata: null,
+        integratorFee: null,
+      },
+      expiry: new BN(Math.floor(Date.now() / 1000) + 600),
+      nonce: new BN(nonce),
+    });
+
+    type Intent = ReturnType<typeof intentFor>;
+
+    // Domain tag, program id, then the Borsh-encoded intent, as in `OrderIntent::signing_message`
+    const signIntent = (intent: Intent) =>
+      Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: gaslessMaker.secretKey,
+        message: Buffer.concat([
+          Buffer.from('fusion_escrow_solana:order_intent'),
+          program.programId.toBuffer(),
+          program.coder.types.encode('OrderIntent', intent),
+        ]),
+      });
+
+    const orderPdas = (intent: Intent) => {
+      const [orderState] = PublicKey.findProgramAddressSync(
+        [Buffer.from('order_source'), gaslessMaker.publicKey.toBuffer(), Buffer.from(intent.terms.orderId)],
+        program.programId
+      );
+      const [escrowAuthority] = PublicKey.findProgramAddressSync(
+        [Buffer.from('escrow_authority'), orderState.toBuffer()],
+        program.programId
+      );
+      const [escrowTokenAccount] = PublicKey.findProgramAddressSync(
+        [Buffer.from('escrow_authority'), orderState.toBuffer(), Buffer.from('token_source')],
+        program.programId
+      );
+      return { orderState, escrowAuthority, escrowTokenAccount };
+    };
+
+    // The maker's only transaction: a delegate approval for the order's escrow authority
+    const approve = (intent: Intent) =>
+      approveChecked(
+        provider.connection,
+        maker.payer,
+        mint,
+        gaslessMakerAccount,
+        orderPdas(intent).escrowAuthority,
+        gaslessMaker,
+        BigInt(intent.terms.amount.toString()),
+        decimals,
+        [],
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    const submit = (intent: Intent, signature: TransactionInstruction) => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = orderPdas(intent);
+      return program.methods
+        .createOrderSourceWithIntent(intent)
+        .accountsPartial({
+          orderStateSource: orderState,
+          ...nullifierPdas(orderState, 'order_source', intent.terms.hashSecret, gaslessMaker.publicKey),
+          config: configPda,
+          resolverRecord: PublicKey.findProgramAddressSync([Buffer.from('resolver'), resolver.publicKey.toBuffer()], program.programId)[0],
+          intentNonce: PublicKey.findProgramAddressSync(
+            [Buffer.from('intent_nonce'), gaslessMaker.publicKey.toBuffer(), intent.nonce.toArrayLike(Buffer, 'le', 8)],
+            program.programId
+          )[0],
+          resolver: resolver.publicKey,
+          makerTokenAccountSource: gaslessMakerAccount,
+          escrowTokenAccountSource: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintSource: mint,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .preInstructions([signature])
+        .signers([resolver])
+        .rpc();
+    };
+
+    it('opens an order from an intent the maker signed and the resolver submits', async () => {
+      const intent = intentFor('intentOpened', 1);
+      await approve(intent);
+
+      // A resolver that changes any signed term no longer matches the maker's signature
+      const tampered = { ...intent, terms: { ...intent.terms, publicCancelRewardBps: 100 } };
+      await expectError(submit(tampered, signIntent(intent)), 'InvalidSignatureInstruction');
+
+      await submit(intent, signIntent(intent));
+      const { orderState, escrowTokenAccount } = orderPdas(intent);
+      const order = await program.account.orderStateSource.fetch(orderState);
+      assert.ok(order.maker.equals(gaslessMaker.publicKey));
+      assert.ok(order.rentPayer.equals(resolver.publicKey), 'the submitting resolver paid rent and the deposit');
+      assert.equal((await tokenBalance(escrowTokenAccount)).toString(), (10 ** 6).toString());
+      assert.equal(await provider.connection.getBalance(gaslessMaker.publicKey), 0, 'the maker spent no SOL');
+    });
+
+    it('rejects a second intent that reuses a nonce', async () => {
+      const intent = intentFor('intentReplayed', 1);
+      await approve(intent);
+      try {
+        await submit(intent, signIntent(intent));
+        assert.fail('intent with a used nonce was accepted');
+      } catch (err) {
+        assert.match(String(err), /already in use|custom program error: 0x0/);
+      }
+    });
+  });
+
+  describe('Native SOL orders', () => {
+    const amount = new BN(LAMPORTS_PER_SOL / 5);
+
+    useQuickTimeouts();
+
+    const openNativeOrder = async (label: string) => {
+      const id = newOrderId(label);
+      const hashlock = Array.from(keccak_256(secretFor(label)));
+      const { orderState } = sourceOrderPdas(id);
+      await program.methods
+        .createOrderSourceNative(id, resolver.publicKey, amount, hashlock, { keccak256: {} }, 1, quickTimelocks, safetyDepositSol, 0, flatAuction(), evmDestination(), null, null, false)
+        .accountsPartial({
+          orderStateSource: orderState,
+          ...nullifierPdas(orderState, 'order_source', hashlock),
+          config: configPda,
+          resolverRecord: PublicKey.findProgramAddressSync([Buffer.from('resolver'), resolver.publicKey.toBuffer()], program.programId)[0],
+          maker: maker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      return { id, hashlock, orderState };
+    };
+
+    const withdrawNative = (order: Awaited<ReturnType<typeof openNativeOrder>>, secret: Buffer) =>
+      program.methods
+        .withdrawSourceNative(order.id, Array.from(secret), 0, [], amount)
+        .accountsPartial({
+          orderStateSource: order.orderState,
+          rentPayer: maker.publicKey,
+          ...nullifierPdas(order.orderState, 'order_source', order.hashlock),
+          secretRecord: secretRecordPda(Array.from(keccak_256(secret))),
+          caller: resolver.publicKey,
+          resolver: resolver.publicKey,
+          config: configPda,
+          treasury: null,
+          integrator: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+
+    it('escrows lamports in the order account apart from its rent and deposit, then releases them', async () => {
+      const order = await openNativeOrder('nativeSource');
+      const orderInfo = await provider.connection.getAccountInfo(order.orderState);
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(orderInfo.data.length);
+      assert.equal(orderInfo.lamports, rent + amount.toNumber() + safetyDepositSol.toNumber());
+      assert.isTrue((await program.account.orderStateSource.fetch(order.orderState)).isNative);
+
+      await sleep(1500); // Past the finality lock
+      const resolverBefore = await provider.connection.getBalance(resolver.publicKey);
+      await withdrawNative(order, secretFor('nativeSource'));
+
+      // The resolver gets the escrowed SOL and the deposit, less the rent of the tombstone and the secret record
+      const { orderNullifier } = nullifierPdas(order.orderState, 'order_source', order.hashlock);
+      const rentPaid =
+        (await provider.connection.getBalance(orderNullifier)) + (await provider.connection.getBalance(secretRecordPda(order.hashlock)));
+      const resolverAfter = await provider.connection.getBalance(resolver.publicKey);
+      assert.equal(resolverAfter - resolverBefore, amount.toNumber() + safetyDepositSol.toNumber() - rentPaid);
+      assert.isNull(await provider.connection.getAccountInfo(order.orderState), 'order closed to the maker');
+    });
+
+    it('keeps the lamports escrowed against a wrong secret', async () => {
+      const order = await openNativeOrder('nativeWrongSecret');
+      await sleep(1500);
+      await expectError(withdrawNative(order, secretFor('notTheNativeSecret')), 'InvalidSecret');
+      const orderInfo = await provider.connection.getAccountInfo(order.orderState);
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(orderInfo.data.length);
+      assert.equal(orderInfo.lamports, rent + amount.toNumber() + safetyDepositSol.toNumber());
+    });
+  });
+
+  describe('Hash algorithms', () => {
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+
+    useQuickTimeouts();
+
+    before(async () => {
+      ({ mint, makerAccount, resolverAccount } = await lifecycleMint());
+    });
+
+    const sha256 = (data: Buffer) => Array.from(createHash('sha256').update(data).digest());
+
+    // Opens a SHA-256 order whose hashlock is `hashlock`, normally sha256 of the label's secret
+    const openSha256Order = async (label: string, hashlock = sha256(secretFor(label))) => {
+      const id = newOrderId(label);
+      const pdas = sourceOrderPdas(id);
+      await program.methods
+        .createOrderSource(id, resolver.publicKey, new BN(10 ** 6), hashlock, { sha256: {} }, 1, quickTimelocks, safetyDepositSol, 0, flatAuction(), evmDestination(), null, null, false)
+        .accountsPartial({
+          orderStateSource: pdas.orderState,
+          maker: maker.publicKey,
+          makerTokenAccountSource: makerAccount,
+          escrowTokenAccountSource: pdas.escrowTokenAccount,
+          escrowAuthority: pdas.escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      return { id, hashlock, secret: secretFor(label), ...pdas };
+    };
+
+    it('unlocks a SHA-256 hashlock, as used by Bitcoin and Lightning, with its preimage', async () => {
+      const order = await openSha256Order('sha256Hashlock');
+      assert.deepEqual((await program.account.orderStateSource.fetch(order.orderState)).hashAlgorithm, { sha256: {} });
+
+      await sleep(1500);
+      const resolverBefore = await tokenBalance(resolverAccount);
+      await withdrawSourceOrder(order, mint, resolverAccount);
+      assert.equal(((await tokenBalance(resolverAccount)) - resolverBefore).toString(), (10 ** 6).toString());
+      const registered = await program.account.secretRecord.fetch(secretRecordPda(order.hashlock));
+      assert.deepEqual(registered.hashAlgorithm, { sha256: {} });
+    });
+
+    it('checks the secret with the algorithm the order was created with', async () => {
+      // A keccak256 hashlock tagged as SHA-256 cannot be opened with its keccak preimage
+      const label = 'sha256TaggedKeccak';
+      const order = await openSha256Order(label, Array.from(keccak_256(secretFor(label))));
+      await sleep(1500);
+      await expectError(
+        withdrawSourceOrder(order, mint, resolverAccount, resolver, secretRecordPda(sha256(secretFor(label)))),
+        'InvalidSecret'
+      );
+    });
+  });
+
+  describe('Settlement fees', () => {
+    const protocolFeeBps = 30;
+    const integratorFee = { recipient: anotherUser.publicKey, feeBps: 50 };
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+    let treasuryFeeAccount: PublicKey;
+    let integratorFeeAccount: PublicKey;
+
+    useQuickTimeouts();
+
+    before(async () => {
+      ({ mint, makerAccount, resolverAccount } = await lifecycleMint());
+      // Any account of the mint owned by the treasury takes protocol fees, not only its ATA
+      treasuryFeeAccount = await createAccount(provider.connection, maker.payer, mint, maker.publicKey, Keypair.generate(), undefined, TOKEN_2022_PROGRAM_ID);
+      integratorFeeAccount = await createAccount(provider.connection, maker.payer, mint, anotherUser.publicKey, undefined, undefined, TOKEN_2022_PROGRAM_ID);
+    });
+
+    // The protocol rate is snapshotted into the order, so it only needs to be set while the order opens
+    const openFeeOrder = async (label: string) => {
+      const id = newOrderId(label);
+      const hashlock = Array.from(keccak_256(secretFor(label)));
+      const pdas = sourceOrderPdas(id);
+      await program.methods.setProtocolFee(protocolFeeBps).accountsPartial({ config: configPda, admin: maker.publicKey }).rpc();
+      try {
+        await program.methods
+          .createOrderSource(id, resolver.publicKey, new BN(10 ** 6), hashlock, { keccak256: {} }, 1, quickTimelocks, safetyDepositSol, 0, flatAuction(), evmDestination(), null, integratorFee, false)
+          .accountsPartial({
+            orderStateSource: pdas.orderState,
+            maker: maker.publicKey,
+            makerTokenAccountSource: makerAccount,
+            escrowTokenAccountSource: pdas.escrowTokenAccount,
+            escrowAuthority: pdas.escrowAuthority,
+            tokenMintSource: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      } finally {
+        await program.methods.setProtocolFee(0).accountsPartial({ config: configPda, admin: maker.publicKey }).rpc();
+      }
+      return { id, hashlock, secret: secretFor(label), ...pdas };
+    };
+
+    const withdrawWithFees = (order: SourceOrder, protocolFeeTokenAccount: PublicKey | null) =>
+      program.methods
+        .withdrawSource(order.id, Array.from(order.secret), 0, [], new BN(10 ** 6))
+        .accountsPartial({
+          orderStateSource: order.orderState,
+          ...nullifierPdas(order.orderState, 'order_source', order.hashlock),
+          secretRecord: secretRecordPda(order.hashlock),
+          caller: resolver.publicKey,
+          resolver: resolver.publicKey,
+          rentPayer: maker.publicKey,
+          resolverTokenAccountSource: resolverAccount,
+          protocolFeeTokenAccount,
+          integratorFeeTokenAccount: integratorFeeAccount,
+          escrowTokenAccountSource: order.escrowTokenAccount,
+          escrowAuthority: order.escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+
+    it('splits a settlement between the resolver, the treasury and the integrator', async () => {
+      const order = await openFeeOrder('feeSplit');
+      assert.equal((await program.account.orderStateSource.fetch(order.orderState)).protocolFeeBps, protocolFeeBps);
+
+      await sleep(1500);
+      // A protocol fee is due, so its account can't be left out
+      await expectError(withdrawWithFees(order, null), 'MissingFeeAccount');
+
+      const [resolverBefore, treasuryBefore, integratorBefore] = await Promise.all(
+        [resolverAccount, treasuryFeeAccount, integratorFeeAccount].map(tokenBalance)
+      );
+      await withdrawWithFees(order, treasuryFeeAccount);
+      const [resolverAfter, treasuryAfter, integratorAfter] = await Promise.all(
+        [resolverAccount, treasuryFeeAccount, integratorFeeAccount].map(tokenBalance)
+      );
+
+      // 1 token: 0.30% (3_000) to the treasury, 0.50% (5_000) to the integrator, the rest to the resolver
+      assert.equal((treasuryAfter - treasuryBefore).toString(), '3000');
+      assert.equal((integratorAfter - integratorBefore).toString(), '5000');
+      assert.equal((resolverAfter - resolverBefore).toString(), '992000');
+    });
+
+    it('takes only the integrator fee the maker asked for from a destination payout', async () => {
+      const label = 'destinationFee';
+      const hashlock = Array.from(keccak_256(secretFor(label)));
+      const source = evmSource();
+      const destination = { ...solanaDestination(mint, quickTimelocks), integratorFee };
+      const id = orderIdFor(hashlock, flatAuction(), source, destination);
+      const pdas = destinationOrderPdas(id);
+      const fill = (descriptor: typeof destination, fee: typeof integratorFee) =>
+        program.methods
+          .fillOrderDestination(id, maker.publicKey, null, new BN(10 ** 6), hashlock, { keccak256: {} }, quickTimelocks, source, new BN(Math.floor(Date.now() / 1000) + 3_600), safetyDepositSol, 0, flatAuction(), descriptor, null, fee)
+          .accountsPartial({
+            orderStateDestination: pdas.orderState,
+            sourceOrder: null,
+            resolver: resolver.publicKey,
+            resolverTokenAccountDestination: resolverAccount,
+            escrowTokenAccountDestination: pdas.escrowTokenAccount,
+            escrowAuthority: pdas.escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([resolver])
+          .rpc();
+
+      // The resolver can neither charge more than the descriptor says nor rewrite the maker's descriptor
+      const resolverFee = { recipient: resolver.publicKey, feeBps: 100 };
+      await expectError(fill(destination, resolverFee), 'DestinationDescriptorMismatch');
+      await expectError(fill({ ...destination, integratorFee: resolverFee }, resolverFee), 'OrderIdMismatch');
+      await fill(destination, integratorFee);
+
+      await sleep(1500);
+      const [makerBefore, integratorBefore] = await Promise.all([makerAccount, integratorFeeAccount].map(tokenBalance));
+      await program.methods
+        .withdrawDestination(id, Array.from(secretFor(label)))
+        .accountsPartial({
+          orderStateDestination: pdas.orderState,
+          ...nullifierPdas(pdas.orderState, 'order_destination', hashlock),
+          secretRecord: secretRecordPda(hashlock),
+          caller: maker.publicKey,
+          maker: maker.publicKey,
+          rentPayer: resolver.publicKey,
+          makerTokenAccountDestination: makerAccount,
+          protocolFeeTokenAccount: null,
+          integratorFeeTokenAccount: integratorFeeAccount,
+          escrowTokenAccountDestination: pdas.escrowTokenAccount,
+          escrowAuthority: pdas.escrowAuthority,
+          instructionsSysvar: null,
+          tokenMintDestination: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      const [makerAfter, integratorAfter] = await Promise.all([makerAccount, integratorFeeAccount].map(tokenBalance));
+      assert.equal((integratorAfter - integratorBefore).toString(), '5000');
+      assert.equal((makerAfter - makerBefore).toString(), '995000');
+    });
+  });
+
+  describe('Relayed destination withdrawals', () => {
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+
+    useQuickTimeouts();
+
+    before(async () => {
+      ({ mint, makerAccount, resolverAccount } = await lifecycleMint());
+    });
+
+    const relayWithdraw = (order: DestinationOrder, secret: Buffer) =>
+      program.methods
+        .relayWithdrawDestination(order.id, Array.from(secret))
+        .accountsPartial({
+          orderStateDestination: order.orderState,
+          rentPayer: resolver.publicKey,
+          ...nullifierPdas(order.orderState, 'order_destination', order.hashlock),
+          secretRecord: secretRecordPda(order.hashlock),
+          relayer: anotherUser.publicKey,
+          maker: maker.publicKey,
+          makerTokenAccountDestination: makerAccount,
+          config: configPda,
+          protocolFeeTokenAccount: null,
+          integratorFeeTokenAccount: null,
+          escrowTokenAccountDestination: order.escrowTokenAccount,
+          escrowAuthority: order.escrowAuthority,
+          tokenMintDestination: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([anotherUser])
+        .rpc();
+
+    it('pays the maker and splits the deposit between the relayer and the maker', async () => {
+      const order = await fillDestinationOrder('relayWithdraw', mint, resolverAccount);
+      await expectError(relayWithdraw(order, order.secret), 'FinalityLockActive');
+
+      await sleep(1500); // Exclusive withdrawal: relaying doesn't wait for the public window
+      await expectError(relayWithdraw(order, secretFor('notTheRelayedSecret')), 'InvalidSecret');
+
+      const makerTokensBefore = await tokenBalance(makerAccount);
+      const makerBefore = await provider.connection.getBalance(maker.publicKey);
+      const relayerBefore = await provider.connection.getBalance(anotherUser.publicKey);
+      const signature = await relayWithdraw(order, order.secret);
+
+      assert.equal((await tokenBalance(makerAccount)) - makerTokensBefore, BigInt(10 ** 6));
+      // Relayer reward is 25% of the deposit; the relayer also pays for the tombstone and the secret record
+      const relayerReward = safetyDepositSol.toNumber() / 4;
+      const { orderNullifier } = nullifierPdas(order.orderState, 'order_destination', order.hashlock);
+      const rentPaid =
+        (await provider.connection.getBalance(orderNullifier)) + (await provider.connection.getBalance(secretRecordPda(order.hashlock)));
+      assert.equal((await provider.connection.getBalance(anotherUser.publicKey)) - relayerBefore, relayerReward - rentPaid);
+      // The provider wallet (the maker) pays the transaction fee and keeps the rest of the deposit
+      const tx = await provider.connection.getTransaction(signature, { commitment: 'confirmed', maxSupportedTransactionVersion: 0 });
+      assert.equal((await provider.connection.getBalance(maker.publicKey)) - makerBefore, safetyDepositSol.toNumber() - relayerReward - tx.meta.fee);
+
+      const nullifier = await program.account.nullifier.fetch(orderNullifier);
+      assert.deepEqual(nullifier.outcome, { withdrawn: {} });
+    });
+  });
+
+  // --- Token-2022 Tests ---
+
+  describe('Token-2022 mints', () => {
+    it('Records the net amount escrowed for a transfer-fee mint and delivers it net of fees', async () => {
+      const feeBps = 100; // 1%
+      const mint = await createMint2022([ExtensionType.TransferFeeConfig], (mint) => [
+        createInitializeTransferFeeConfigInstruction(
+          mint,
+          maker.publicKey,
+          maker.publicKey,
+          feeBps,
+          BigInt(mintAmount),
+          TOKEN_2022_PROGRAM_ID
+        ),
+      ]);
+      const { makerAccount, resolverAccount } = await fundedAccounts(mint);
+
+      const id = newOrderId('token2022FeeOrder');
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id);
+      const orderSecret = Buffer.from('token2022FeeSecret'.padEnd(32, '\0'));
+      const amount = new BN(100 * 10 ** 6);
+      const expectedNet = amount.muln(10_000 - feeBps).divn(10_000);
+
+      await program.methods
+        .createOrderSource(
+          id,
+          resolver.publicKey,
+          amount,
+          Array.from(keccak_256(orderSecret)),
+          { keccak256: {} },
+          1,
+          timelocks,
+          safetyDepositSol,
+          0,
+          flatAuction(),
+          evmDestination(),
+          null, // No donation metadata
+          null, // No integrator fee
+          false // Free-form order id
+        )
+        .accountsPartial({
+          orderStateSource: orderState,
+          maker: maker.publicKey,
+          makerTokenAccountSource: makerAccount,
+          escrowTokenAccountSource: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const order = await program.account.orderStateSource.fetch(orderState);
+      assert.ok(order.amount.eq(expectedNet), 'order records what the escrow received');
+      assert.ok(order.destination.chainId.eqn(1));
+      assert.deepEqual(Buffer.from(order.destination.token), evmDestination().token, 'destination token kept byte for byte');
+      const escrow = await getAccount(provider.connection, escrowTokenAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      assert.equal(escrow.amount.toString(), expectedNet.toString());
+
+      // The completing fill tombstones both the order account and the hashlock
+      const [orderNullifier] = PublicKey.findProgramAddressSync(
+        [Buffer.from('nullifier'), orderState.toBuffer()],
+        program.programId
+      );
+      const hashlockNullifier = hashlockNullifierPda('order_source', order.hashSecret);
+      const [secretRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from('secret'), Buffer.from(keccak_256(orderSecret))],
+        program.programId
+      );
+
+      await sleep(2000); // Past the finality lock
+      await program.methods
+        .withdrawSource(id, Array.from(orderSecret), 0, [], order.amount)
+        .accountsPartial({
+          orderStateSource: orderState,
+          caller: resolver.publicKey,
+          resolver: resolver.publicKey,
+          rentPayer: maker.publicKey,
+          resolverTokenAccountSource: resolverAccount,
+          escrowTokenAccountSource: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintSource: mint,
+          protocolFeeTokenAccount: null,
+          integratorFeeTokenAccount: null,
+          orderNullifier,
+          hashlockNullifier,
+          secretRecord,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+
+      const resolverBalance = await getAccount(provider.connection, resolverAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      const expectedDelivered = expectedNet.muln(10_000 - feeBps).divn(10_000);
+      assert.equal(resolverBalance.amount.toString(), expectedDelivered.toString());
+      assert.isNull(await provider.connection.getAccountInfo(escrowTokenAccount), 'escrow closed after harvesting fees');
+
+      const nullifier = await program.account.nullifier.fetch(hashlockNullifier);
+      assert.ok(nullifier.order.equals(orderState));
+      assert.deepEqual(nullifier.outcome, { withdrawn: {} });
+      assert.deepEqual(Buffer.from(nullifier.secret), orderSecret, 'revealed secret is kept on chain');
+      assert.isNotNull(await provider.connection.getAccountInfo(orderNullifier));
+
+      // Watchers read the preimage from the registry, by account fetch or through the view instruction
+      const registered = await program.account.secretRecord.fetch(secretRecord);
+      assert.deepEqual(Buffer.from(registered.secret), orderSecret);
+      const viewed = await program.methods.registeredSecret(Array.from(keccak_256(orderSecret))).accounts({}).view();
+      assert.deepEqual(Buffer.from(viewed), orderSecret);
+    });
+
+    it('Forwards extra account metas for a transfer-hook mint', async () => {
+      const transferHookProgram = hookProgram.programId;
+      const mint = await createMint2022([ExtensionType.TransferHook], (mint) => [
+        createInitializeTransferHookInstruction(mint, maker.publicKey, transferHookProgram, TOKEN_2022_PROGRAM_ID),
+      ]);
+
+      // The fixture asks Token-2022 for one extra account, a per-mint counter it bumps on every transfer
+      const extraAccountMetaList = getExtraAccountMetaAddress(mint, transferHookProgram);
+      const [counter] = PublicKey.findProgramAddressSync([Buffer.from('counter'), mint.toBuffer()], transferHookProgram);
+      await hookProgram.methods
+        .initializeExtraAccountMetaList()
+        .accountsPartial({ extraAccountMetaList, mint, payer: maker.publicKey, counter })
+        .rpc();
+
+      const { makerAccount } = await fundedAccounts(mint);
+      const id = newOrderId('token2022HookOrder');
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id);
+      const amount = new BN(10 * 10 ** 6);
+
+      // Resolve the hook's accounts the same way a client would for a plain transfer_checked
+      const resolveIx = new TransactionInstruction({
+        programId: TOKEN_2022_PROGRAM_ID,
+        keys: [
+          { pubkey: makerAccount, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: maker.publicKey, isSigner: true, isWritable: false },
+        ],
+      });
+      await addExtraAccountMetasForExecute(
+        provider.connection,
+        resolveIx,
+        transferHookProgram,
+        makerAccount,
+        mint,
+        escrowTokenAccount,
+        maker.publicKey,
+        BigInt(amount.toString())
+      );
+      const hookAccounts = resolveIx.keys.slice(4);
+      assert.isTrue(
+        hookAccounts.some((meta) => meta.pubkey.equals(counter)),
+        'counter, hook program and validation account are forwarded'
+      );
+
+      await program.methods
+        .createOrderSource(
+          id,
+          resolver.publicKey,
+          amount,
+          Array.from(keccak_256(Buffer.from('token2022HookSecret'.padEnd(32, '\0')))),
+          { keccak256: {} },
+          1,
+          timelocks,
+          safetyDepositSol,
+          0,
+          flatAuction(),
+          evmDestination(),
+          null, // No donation metadata
+          null, // No integrator fee
+          false // Free-form order id
+        )
+        .accountsPartial({
+          orderStateSource: orderState,
+          maker: maker.publicKey,
+          makerTokenAccountSource: makerAccount,
+          escrowTokenAccountSource: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .remainingAccounts(hookAccounts)
+        .rpc();
+
+      const order = await program.account.orderStateSource.fetch(orderState);
+      assert.ok(order.amount.eq(amount));
+      const transfers = await hookProgram.account.transferCounter.fetch(counter);
+      assert.equal(transfers.transfers.toNumber(), 1, 'the hook ran on the escrow deposit');
+      assert.ok(transfers.transferred.eq(amount));
+    });
+  });
+
+  describe('recipient token account validation', () => {
+    const orderSecret = Buffer.from('recipientChecksSecret'.padEnd(32, '\0'));
+    const hashSecret = Array.from(keccak_256(orderSecret));
+    const sourceId = newOrderId('recipientChecksSource');
+    let destinationId: number[]; // Bound to the fill terms, set once the mint exists
+    let mint: PublicKey;
+    let makerAccount: PublicKey;
+    let resolverAccount: PublicKey;
+    let strangerAccount: PublicKey; // Right mint, owned by neither party
+
+    const nullifiers = (orderState: PublicKey, side: string) => ({
+      orderNullifier: PublicKey.findProgramAddressSync([Buffer.from('nullifier'), orderState.toBuffer()], program.programId)[0],
+      hashlockNullifier: hashlockNullifierPda(side, hashSecret),
+    });
+
+    const [secretRecord] = PublicKey.findProgramAddressSync(
+      [Buffer.from('secret'), Buffer.from(hashSecret)],
+      program.programId
+    );
+
+    // Every order reserves its hashlock, so tests that open extra orders pass one of their own
+    const freshHashlock = (label: string) => Array.from(keccak_256(Buffer.from(label)));
+
+    const createSource = (id: number[], makerTokenAccountSource: PublicKey, hashlock = hashSecret) => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(id);
+      return program.methods
+        .createOrderSource(id, resolver.publicKey, new BN(10 ** 6), hashlock, { keccak256: {} }, 1, timelocks, safetyDepositSol, 0, flatAuction(), evmDestination(), null, null, false)
+        .accountsPartial({
+          orderStateSource: orderState,
+          maker: maker.publicKey,
+          makerTokenAccountSource,
+          escrowTokenAccountSource: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    };
+
+    // Fills of Ethereum orders must carry the order id bound to their terms
+    const fillId = (hashlock: number[], source = evmSource()) => orderIdFor(hashlock, flatAuction(), source, solanaDestination(mint));
+
+    const fillDestination = (
+      resolverTokenAccountDestination: PublicKey,
+      sourceCancellation = new BN(Math.floor(Date.now() / 1000) + 3_600),
+      source = evmSource(),
+      hashlock = hashSecret
+    ) => {
+      const id = fillId(hashlock, source);
+      const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(id);
+      return program.methods
+        .fillOrderDestination(id, maker.publicKey, null, new BN(10 ** 6), hashlock, { keccak256: {} }, timelocks, source, sourceCancellation, safetyDepositSol, 0, flatAuction(), solanaDestination(mint), null, null)
+        .accountsPartial({
+          orderStateDestination: orderState,
+          sourceOrder: null,
+          resolver: resolver.publicKey,
+          resolverTokenAccountDestination,
+          escrowTokenAccountDestination: escrowTokenAccount,
+          escrowAuthority,
+          tokenMintDestination: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+    };
+
+    const expectMisrouteRejected = async (tx: Promise<unknown>) => {
+      try {
+        await tx;
+        assert.fail('misrouted token account was accepted');
+      } catch (err) {
+        assert.match(String(err), /ConstraintTokenOwner|ConstraintAssociated|AccountNotAssociatedTokenAccount/);
+      }
+    };
+
+    before(async () => {
+      mint = await createMint2022([], () => []);
+      ({ makerAccount, resolverAccount } = await fundedAccounts(mint));
+      await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+      strangerAccount = await createAccount(
+        provider.connection,
+        maker.payer,
+        mint,
+        Keypair.generate().publicKey,
+        undefined,
+        undefined,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      await createSource(sourceId, makerAccount);
+      destinationId = fillId(hashSecret);
+      await fillDestination(resolverAccount);
+    });
+
+    it('rejects a maker_token_account_source the maker does not own on creation', async () => {
+      await expectMisrouteRejected(createSource(newOrderId('recipientChecksStranger'), strangerAccount, freshHashlock('recipientChecksStranger')));
+    });
+
+    it('rejects a resolver_token_account_source other than the resolver ATA on withdrawal', async () => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(sourceId);
+      await expectMisrouteRejected(
+        program.methods
+          .withdrawSource(sourceId, Array.from(orderSecret), 0, [], new BN(10 ** 6))
+          .accountsPartial({
+            orderStateSource: orderState,
+            orderNullifier: null,
+            hashlockNullifier: null,
+            secretRecord,
+            caller: resolver.publicKey,
+            resolver: resolver.publicKey,
+            rentPayer: maker.publicKey,
+            resolverTokenAccountSource: makerAccount,
+            protocolFeeTokenAccount: null,
+            integratorFeeTokenAccount: null,
+            escrowTokenAccountSource: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintSource: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([resolver])
+          .rpc()
+      );
+    });
+
+    it('rejects a maker_token_account_source other than the maker ATA on cancellation', async () => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(sourceId);
+      await expectMisrouteRejected(
+        program.methods
+          .cancelSource(sourceId)
+          .accountsPartial({
+            orderStateSource: orderState,
+            ...nullifiers(orderState, 'order_source'),
+            maker: maker.publicKey,
+            rentPayer: maker.publicKey,
+            makerTokenAccountSource: resolverAccount,
+            canceller: resolver.publicKey,
+            resolverStake: null,
+            treasury: null,
+            secretRecord,
+            destinationHashlockNullifier: nullifiers(orderState, 'order_destination').hashlockNullifier,
+            escrowTokenAccountSource: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintSource: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([resolver])
+          .rpc()
+      );
+    });
+
+    it('rejects a resolver_token_account_destination the resolver does not own on fill', async () => {
+      await expectMisrouteRejected(fillDestination(strangerAccount, undefined, undefined, freshHashlock('recipientChecksStrangerFill')));
+    });
+
+    it('rejects a maker_token_account_destination other than the maker ATA on withdrawal', async () => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(destinationId);
+      await expectMisrouteRejected(
+        program.methods
+          .withdrawDestination(destinationId, Array.from(orderSecret))
+          .accountsPartial({
+            orderStateDestination: orderState,
+            ...nullifiers(orderState, 'order_destination'),
+            secretRecord,
+            caller: maker.publicKey,
+            maker: maker.publicKey,
+            rentPayer: resolver.publicKey,
+            makerTokenAccountDestination: resolverAccount,
+            protocolFeeTokenAccount: null,
+            integratorFeeTokenAccount: null,
+            escrowTokenAccountDestination: escrowTokenAccount,
+            escrowAuthority,
+            instructionsSysvar: null,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc()
+      );
+    });
+
+    it('rejects a destination fill that leaves no safety margin before the source deadline', async () => {
+      // timelocks.cancellation is 1_200s, so with the 600s margin the source order must stay open for 1_800s
+      const sourceCancellation = new BN(Math.floor(Date.now() / 1000) + 1_500);
+      try {
+        await fillDestination(resolverAccount, sourceCancellation, undefined, freshHashlock('timeoutPolicyMargin'));
+        assert.fail('destination deadline too close to the source deadline was accepted');
+      } catch (err) {
+        assert.match(String(err), /InsufficientSafetyMargin/);
+      }
+    });
+
+    it('requires the source order account when the source leg is on Solana', async () => {
+      const solanaSource = evmSource(501);
+      try {
+        await fillDestination(resolverAccount, undefined, solanaSource, freshHashlock('sameChainWithoutSource'));
+        assert.fail('same-chain fill without its source order was accepted');
+      } catch (err) {
+        assert.match(String(err), /MissingSourceOrder/);
+      }
+    });
+
+    it('refunds rent only to the account that paid it', async () => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(destinationId);
+      try {
+        await program.methods
+          .cancelDestination(destinationId)
+          .accountsPartial({
+            orderStateDestination: orderState,
+            ...nullifiers(orderState, 'order_destination'),
+            resolver: resolver.publicKey,
+            rentPayer: maker.publicKey,
+            resolverTokenAccountDestination: resolverAccount,
+            canceller: maker.publicKey,
+            escrowTokenAccountDestination: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        assert.fail('rent was refunded to an account that did not pay it');
+      } catch (err) {
+        assert.match(String(err), /InvalidRentPayer/);
+      }
+      const order = await program.account.orderStateDestination.fetch(orderState);
+      assert.ok(order.rentPayer.equals(resolver.publicKey), 'the filling resolver pays the destination rent');
+    });
+
+    it('rejects a resolver_token_account_destination other than the resolver ATA on cancellation', async () => {
+      const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(destinationId);
+      await expectMisrouteRejected(
+        program.methods
+          .cancelDestination(destinationId)
+          .accountsPartial({
+            orderStateDestination: orderState,
+            ...nullifiers(orderState, 'order_destination'),
+            resolver: resolver.publicKey,
+            rentPayer: resolver.publicKey,
+            resolverTokenAccountDestination: makerAccount,
+            canceller: maker.publicKey,
+            escrowTokenAccountDestination: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc()
+      );
+    });
+  });
+
+  describe('same-chain donations', () => {
+    const orderSecret = Buffer.from('atomicSettleSecret'.padEnd(32, '\0'));
+    const hashSecret = Array.from(keccak_256(orderSecret));
+    const id = newOrderId('atomicSettle');
+    const amount = new BN(10 ** 6);
+    const amountOnDestination = new BN(2 * 10 ** 6);
+    // Same-chain legs cannot use the cross-chain timelocks: the destination must close 600s before the source
+    const destinationTimelocks = { withdrawal: 1, publicWithdrawal: 100, cancellation: 300, publicCancellation: 400 };
+    // Both legs carry the same donation so the creator sees it in the destination withdrawal event
+    const metadata = { version: 1, linkId: 'donnut-link-42', message: 'Keep the streams coming! 🍩', donorName: 'anon-fan' };
+
+    it('releases both legs in one instruction', async () => {
+      const sourceMint = await createMint2022([], () => []);
+      const destinationMint = await createMint2022([], () => []);
+      const { makerAccount: makerSourceAccount } = await fundedAccounts(sourceMint);
+      const { makerAccount: makerDestinationAccount, resolverAccount: resolverDestinationAccount } =
+        await fundedAccounts(destinationMint);
+      await mintTo(provider.connection, maker.payer, destinationMint, resolverDestinationAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+      const source = sourceOrderPdas(id);
+      const destination = destinationOrderPdas(id);
+      const nullifier = (seeds: Buffer[]) => PublicKey.findProgramAddressSync([Buffer.from('nullifier'), ...seeds], program.programId)[0];
+      const [secretRecord] = PublicKey.findProgramAddressSync([Buffer.from('secret'), Buffer.from(hashSecret)], program.programId);
+
+      // The maker asks to be paid in the destination mint on Solana itself
+      await program.methods
+        .createOrderSource(
+          id,
+          resolver.publicKey,
+          amount,
+          hashSecret,
+          { keccak256: {} },
+          1,
+          timelocks,
+          safetyDepositSol,
+          0,
+          flatAuction(),
+          solanaDestination(destinationMint, destinationTimelocks),
+          metadata,
+          null,
+          false
+        )
+        .accountsPartial({
+          orderStateSource: source.orderState,
+          orderNullifier: nullifier([source.orderState.toBuffer()]),
+          hashlockNullifier: nullifier([Buffer.from('order_source'), maker.publicKey.toBuffer(), Buffer.from(hashSecret)]),
+          maker: maker.publicKey,
+          makerTokenAccountSource: makerSourceAccount,
+          escrowTokenAccountSource: source.escrowTokenAccount,
+          escrowAuthority: source.escrowAuthority,
+          tokenMintSource: sourceMint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const sourceOrder = await program.account.orderStateSource.fetch(source.orderState);
+      assert.deepEqual(sourceOrder.metadata, metadata, 'donation stored with the order');
+      await program.methods
+        .fillOrderDestination(
+          id,
+          maker.publicKey,
+          null,
+          amountOnDestination,
+          hashSecret,
+          { keccak256: {} },
+          destinationTimelocks,
+          { chainId: new BN(501), escrow: source.orderState.toBuffer(), token: sourceMint.toBuffer(), amount, timelocks },
+          sourceOrder.creationTimestamp.addn(timelocks.cancellation),
+          safetyDepositSol,
+          0,
+          flatAuction(),
+          solanaDestination(destinationMint, destinationTimelocks),
+          metadata,
+          null
+        )
+        .accountsPartial({
+          orderStateDestination: destination.orderState,
+          orderNullifier: nullifier([destination.orderState.toBuffer()]),
+          hashlockNullifier: nullifier([Buffer.from('order_destination'), Buffer.from(hashSecret)]),
+          sourceOrder: source.orderState,
+          resolver: resolver.publicKey,
+          resolverTokenAccountDestination: resolverDestinationAccount,
+          escrowTokenAccountDestination: destination.escrowTokenAccount,
+          escrowAuthority: destination.escrowAuthority,
+          tokenMintDestination: destinationMint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+
+      const makerBefore = await getAccount(provider.connection, makerDestinationAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      const withdrawn = new Promise<any>((resolve) => {
+        const listener = program.addEventListener('makerWithdrawnDestination', (event) => {
+          program.removeEventListener(listener);
+          resolve(event);
+        });
+      });
+      // The maker posted the source deposit and paid the source rent, so it gets all of the source lamports back
+      const sourceLamports =
+        (await provider.connection.getBalance(source.orderState)) + (await provider.connection.getBalance(source.escrowTokenAccount));
+      const makerLamportsBefore = await provider.connection.getBalance(maker.publicKey);
+
+      // No finality lock to wait for: both legs confirm in the same transaction
+      const settleSignature = await program.methods
+        .atomicSettle(id, Array.from(orderSecret))
+        .accountsPartial({
+          orderStateSource: source.orderState,
+          orderStateDestination: destination.orderState,
+          sourceRentPayer: maker.publicKey,
+          destinationRentPayer: resolver.publicKey,
+          sourceOrderNullifier: nullifier([source.orderState.toBuffer()]),
+          sourceHashlockNullifier: nullifier([Buffer.from('order_source'), maker.publicKey.toBuffer(), Buffer.from(hashSecret)]),
+          destinationOrderNullifier: nullifier([destination.orderState.toBuffer()]),
+          destinationHashlockNullifier: nullifier([Buffer.from('order_destination'), Buffer.from(hashSecret)]),
+          secretRecord,
+          resolver: resolver.publicKey,
+          maker: maker.publicKey,
+          protocolFeeTokenAccountSource: null,
+          integratorFeeTokenAccountSource: null,
+          protocolFeeTokenAccountDestination: null,
+          integratorFeeTokenAccountDestination: null,
+          escrowTokenAccountSource: source.escrowTokenAccount,
+          escrowAuthoritySource: source.escrowAuthority,
+          escrowTokenAccountDestination: destination.escrowTokenAccount,
+          escrowAuthorityDestination: destination.escrowAuthority,
+          tokenMintSource: sourceMint,
+          tokenMintDestination: destinationMint,
+          tokenProgramSource: TOKEN_2022_PROGRAM_ID,
+          tokenProgramDestination: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([resolver])
+        .rpc();
+
+      const makerAfter = await getAccount(provider.connection, makerDestinationAccount, undefined, TOKEN_2022_PROGRAM_ID);
+      assert.equal((makerAfter.amount - makerBefore.amount).toString(), amountOnDestination.toString());
+      await provider.connection.confirmTransaction(settleSignature, 'confirmed');
+      const settleTx = await provider.connection.getTransaction(settleSignature, { commitment: 'confirmed', maxSupportedTransactionVersion: 0 });
+      const makerLamportsAfter = await provider.connection.getBalance(maker.publicKey);
+      assert.equal(makerLamportsAfter - makerLamportsBefore, sourceLamports - settleTx.meta.fee, 'source deposit goes back to the maker who posted it');
+      assert.isNull(await provider.connection.getAccountInfo(source.orderState), 'source order closed');
+      assert.isNull(await provider.connection.getAccountInfo(destination.orderState), 'destination order closed');
+      const registered = await program.account.secretRecord.fetch(secretRecord);
+      assert.deepEqual(Buffer.from(registered.secret), orderSecret);
+      assert.deepEqual((await withdrawn).metadata, metadata, 'creator dashboard reads the message from the event');
+    });
+
+    it('rejects a fill of a partial-fill source order', async () => {
+      const mint = await createMint2022([], () => []);
+      const { makerAccount, resolverAccount } = await fundedAccounts(mint);
+      await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+      const partialId = newOrderId('partialSameChain');
+      // Merkle root of the secret tree; a destination escrow locked on it could never be withdrawn
+      const root = Array.from(keccak_256(Buffer.from('partialSameChainRoot')));
+      const source = sourceOrderPdas(partialId);
+      const destination = destinationOrderPdas(partialId);
+      await program.methods
+        .createOrderSource(partialId, resolver.publicKey, amount, root, { keccak256: {} }, 2, timelocks, safetyDepositSol, 0, flatAuction(), solanaDestination(mint, destinationTimelocks), null, null, false)
+        .accountsPartial({
+          orderStateSource: source.orderState,
+          maker: maker.publicKey,
+          makerTokenAccountSource: makerAccount,
+          escrowTokenAccountSource: source.escrowTokenAccount,
+          escrowAuthority: source.escrowAuthority,
+          tokenMintSource: mint,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const sourceOrder = await program.account.orderStateSource.fetch(source.orderState);
+      await expectError(
+        program.methods
+          .fillOrderDestination(
+            partialId,
+            maker.publicKey,
+            null,
+            amount,
+            root,
+            { keccak256: {} },
+            destinationTimelocks,
+            { chainId: new BN(501), escrow: source.orderState.toBuffer(), token: mint.toBuffer(), amount, timelocks },
+            sourceOrder.creationTimestamp.addn(timelocks.cancellation),
+            safetyDepositSol,
+            0,
+            flatAuction(),
+            solanaDestination(mint, destinationTimelocks),
+            null,
+            null
+          )
+          .accountsPartial({
+            orderStateDestination: destination.orderState,
+            sourceOrder: source.orderState,
+            resolver: resolver.publicKey,
+            resolverTokenAccountDestination: resolverAccount,
+            escrowTokenAccountDestination: destination.escrowTokenAccount,
+            escrowAuthority: destination.escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([resolver])
+          .rpc(),
+        'PartialSourceOrder'
+      );
+    });
+
+    it('rejects a cross-chain fill carrying a donation the maker did not sign', async () => {
+      const mint = await createMint2022([], () => []);
+      const { resolverAccount } = await fundedAccounts(mint);
+      await mintTo(provider.connection, maker.payer, mint, resolverAccount, maker.publicKey, mintAmount, [], undefined, TOKEN_2022_PROGRAM_ID);
+      const hashlock = Array.from(keccak_256(Buffer.from('unsignedDonation')));
+      const source = evmSource();
+      const signed = { ...metadata, donorName: null };
+      const fill = (id: number[], fillMetadata: typeof signed) => {
+        const { orderState, escrowAuthority, escrowTokenAccount } = destinationOrderPdas(id);
+        return program.methods
+          .fillOrderDestination(id, maker.publicKey, null, amount, hashlock, { keccak256: {} }, destinationTimelocks, source, new BN(Math.floor(Date.now() / 1000) + 3_600), safetyDepositSol, 0, flatAuction(), solanaDestination(mint, destinationTimelocks), fillMetadata, null)
+          .accountsPartial({
+            orderStateDestination: orderState,
+            sourceOrder: null,
+            resolver: resolver.publicKey,
+            resolverTokenAccountDestination: resolverAccount,
+            escrowTokenAccountDestination: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintDestination: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([resolver])
+          .rpc();
+      };
+
+      // The id of an order without a donation can't be filled with one, nor can a signed donation be edited
+      const plainId = orderIdFor(hashlock, flatAuction(), source, solanaDestination(mint, destinationTimelocks));
+      await expectError(fill(plainId, signed), 'OrderIdMismatch');
+      const donationId = orderIdFor(hashlock, flatAuction(), source, solanaDestination(mint, destinationTimelocks), 0, signed);
+      await expectError(fill(donationId, { ...signed, message: 'Sent by the resolver' }), 'OrderIdMismatch');
+      await fill(donationId, signed);
+      const filled = await program.account.orderStateDestination.fetch(destinationOrderPdas(donationId).orderState);
+      assert.deepEqual(filled.metadata, signed);
+    });
+
+    it('rejects donation messages over the size limit', async () => {
+      const mint = await createMint2022([], () => []);
+      const { makerAccount } = await fundedAccounts(mint);
+      const { orderState, escrowAuthority, escrowTokenAccount } = sourceOrderPdas(newOrderId('oversizedDonation'));
+      try {
+        await program.methods
+          .createOrderSource(
+            newOrderId('oversizedDonation'),
+            resolver.publicKey,
+            amount,
+            Array.from(keccak_256(Buffer.from('oversizedDonation'))),
+            { keccak256: {} },
+            1,
+            timelocks,
+            safetyDepositSol,
+            0,
+            flatAuction(),
+            evmDestination(),
+            { ...metadata, message: 'x'.repeat(281) },
+            null,
+            false
+          )
+          .accountsPartial({
+            orderStateSource: orderState,
+            maker: maker.publicKey,
+            makerTokenAccountSource: makerAccount,
+            escrowTokenAccountSource: escrowTokenAccount,
+            escrowAuthority,
+            tokenMintSource: mint,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+        assert.fail('oversized donation message was accepted');
+      } catch (err) {
+        assert.match(String(err), /InvalidDonationMetadata/);
+      }
+    });
+  });
+
 });